@@ -0,0 +1,109 @@
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::camera::Camera;
+use rs_tracer::color::Background;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{DiffuseLight, Lambertian, MatType};
+use rs_tracer::quad::{AaRect, Cuboid, Plane, Quad};
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render_with_lights, ImageConfig};
+use std::sync::Arc;
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let red = MatType::Lambertian(Lambertian::new(Vec3::new(0.65, 0.05, 0.05)));
+    let white = MatType::Lambertian(Lambertian::new(Vec3::new(0.73, 0.73, 0.73)));
+    let green = MatType::Lambertian(Lambertian::new(Vec3::new(0.12, 0.45, 0.15)));
+    let light = MatType::DiffuseLight(DiffuseLight::new(Vec3::new(15., 15., 15.)));
+
+    // walls
+    world.add(Arc::new(Quad::new(
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        Vec3::new(0., 0., 555.),
+        green,
+    )));
+    world.add(Arc::new(Quad::new(
+        Vec3::new(0., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        Vec3::new(0., 0., 555.),
+        red,
+    )));
+    world.add(Arc::new(Quad::new(
+        Vec3::new(0., 0., 0.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 0., 555.),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        Vec3::new(555., 555., 555.),
+        Vec3::new(-555., 0., 0.),
+        Vec3::new(0., 0., -555.),
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        Vec3::new(0., 0., 555.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        white.clone(),
+    )));
+
+    // ceiling light, also sampled directly
+    let ceiling_light = Arc::new(AaRect::new(
+        Plane::Xz,
+        (213., 343.),
+        (227., 332.),
+        554.,
+        light,
+    ));
+    world.add(ceiling_light.clone());
+
+    let mut lights = HittableList::new();
+    lights.add(ceiling_light);
+
+    // blocks
+    world.add(Arc::new(Cuboid::new(
+        Vec3::new(130., 0., 65.),
+        Vec3::new(295., 165., 230.),
+        white.clone(),
+    )));
+    world.add(Arc::new(Cuboid::new(
+        Vec3::new(265., 0., 295.),
+        Vec3::new(430., 330., 460.),
+        white,
+    )));
+
+    let world = BvhNode::new(&world, 0., 1.);
+
+    let config = ImageConfig {
+        aspect_ratio: 1.,
+        width: 400,
+        height: 400,
+        samples_per_pixel: 200,
+        background: Background::Black,
+        ..Default::default()
+    };
+
+    let lookfrom = Vec3::new(278., 278., -800.);
+    let lookat = Vec3::new(278., 278., 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = 10.;
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        40.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render_with_lights(world, lights, camera, config);
+
+    match img.save("cornell_box.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
@@ -0,0 +1,136 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable::HitRecord;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Lambertian, MatType, Material, ScatterRecord};
+use rs_tracer::ray::Ray;
+use rs_tracer::sampler::Sampler;
+use rs_tracer::sphere::Sphere;
+use rs_tracer::vec3::{dot, unit_vector, Vec3};
+use rs_tracer::{render, ImageConfig};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Oren-Nayar rough diffuse reflection, defined outside the crate.
+struct OrenNayar {
+    albedo: Vec3,
+    a: f64,
+    b: f64,
+}
+
+impl OrenNayar {
+    fn new(albedo: Vec3, sigma: f64) -> Self {
+        let s2 = sigma * sigma;
+        OrenNayar {
+            albedo,
+            a: 1. - 0.5 * s2 / (s2 + 0.33),
+            b: 0.45 * s2 / (s2 + 0.09),
+        }
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        // cosine-weighted sampling, as for Lambertian
+        let mut dir = rec.norm + Vec3::random_unit(sampler);
+        if dir.near_zero() {
+            dir = rec.norm;
+        }
+
+        let wi = unit_vector(dir);
+        let wo = -unit_vector(r_in.dir);
+        let pdf = self.pdf(rec, wi, wo);
+        if pdf <= 0. {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir,
+                time: r_in.time,
+            },
+            throughput: self.eval(rec, wi, wo) / pdf,
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        let cos_i = dot(wi, rec.norm);
+        let cos_o = dot(wo, rec.norm).min(1.);
+        if cos_i <= 0. || cos_o <= 0. {
+            return Vec3::new(0., 0., 0.);
+        }
+
+        let sin_i = (1. - cos_i * cos_i).max(0.).sqrt();
+        let sin_o = (1. - cos_o * cos_o).max(0.).sqrt();
+
+        // cosine of the azimuth difference, from the tangent plane projections
+        let ti = wi - cos_i * rec.norm;
+        let to = wo - cos_o * rec.norm;
+        let cos_phi = if ti.near_zero() || to.near_zero() {
+            0.
+        } else {
+            dot(unit_vector(ti), unit_vector(to)).max(0.)
+        };
+
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
+        } else {
+            (sin_i, sin_o / cos_o)
+        };
+
+        self.albedo * ((self.a + self.b * cos_phi * sin_alpha * tan_beta) * cos_i / PI)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, _wo: Vec3) -> f64 {
+        dot(wi, rec.norm).max(0.) / PI
+    }
+}
+
+fn main() {
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
+    )));
+
+    // smooth Lambertian next to a very rough Oren-Nayar ball
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(-1.1, 1., 0.),
+        1.,
+        MatType::Lambertian(Lambertian::new(Vec3::new(0.8, 0.4, 0.3))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(1.1, 1., 0.),
+        1.,
+        MatType::custom(OrenNayar::new(Vec3::new(0.8, 0.4, 0.3), 1.)),
+    )));
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 2., 8.);
+    let lookat = Vec3::new(0., 1., 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("custom_material.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
@@ -4,40 +4,41 @@ use rs_tracer::material::{Dielectric, Lambertian, MatType, Metal};
 use rs_tracer::sphere::Sphere;
 use rs_tracer::vec3::Vec3;
 use rs_tracer::{render, ImageConfig};
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() {
     // create a scene
     let mut world = HittableList::new();
 
-    let mat_ground = MatType::Lambertian(Lambertian {
-        albedo: Vec3::new(0.8, 0.8, 0.),
-    });
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.8, 0.8, 0.)));
 
-    world.add(Rc::new(Sphere::new(
+    world.add(Arc::new(Sphere::new(
         Vec3::new(0., -100.5, -1.),
         100.,
         mat_ground,
     )));
 
-    let mat_cent = MatType::Lambertian(Lambertian {
-        albedo: Vec3::new(0.1, 0.2, 0.5),
-    });
+    let mat_cent = MatType::Lambertian(Lambertian::new(Vec3::new(0.1, 0.2, 0.5)));
     let mat_left = MatType::Dielectric(Dielectric {
         index_refraction: 1.5,
     });
-    let mat_right = MatType::Metal(Metal {
-        albedo: Vec3::new(0.8, 0.6, 0.2),
-        fuzz: 0.,
-    });
-    world.add(Rc::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5, mat_cent)));
-    world.add(Rc::new(Sphere::new(Vec3::new(-1., 0., -1.), 0.5, mat_left)));
-    world.add(Rc::new(Sphere::new(
+    let mat_right = MatType::Metal(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.));
+    world.add(Arc::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5, mat_cent)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(-1., 0., -1.),
+        0.5,
+        mat_left.clone(),
+    )));
+    world.add(Arc::new(Sphere::new(
         Vec3::new(-1., 0., -1.),
         -0.4,
         mat_left,
     )));
-    world.add(Rc::new(Sphere::new(Vec3::new(1., 0., -1.), 0.5, mat_right)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(1., 0., -1.),
+        0.5,
+        mat_right,
+    )));
 
     let config = ImageConfig::default();
 
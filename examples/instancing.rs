@@ -0,0 +1,111 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable::Hittable;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Conductor, Lambertian, MatType};
+use rs_tracer::mesh::TriangleMesh;
+use rs_tracer::sphere::Sphere;
+use rs_tracer::transform::{Instance, Transform};
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// A tuft of curved, tapering grass blades standing on the origin.
+fn grass_tuft(blades: usize, segments: usize, material: MatType) -> TriangleMesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+
+    for b in 0..blades {
+        let angle = 2. * PI * b as f64 / blades as f64;
+        let (dir, side) = (
+            Vec3::new(angle.cos(), 0., angle.sin()),
+            Vec3::new(-angle.sin(), 0., angle.cos()),
+        );
+        let base = positions.len();
+
+        for s in 0..=segments {
+            let h = s as f64 / segments as f64;
+            let width = 0.05 * (1. - h);
+            let center = Vec3::new(0., h, 0.) + 0.3 * h * h * dir;
+            positions.push(center - width * side);
+            positions.push(center + width * side);
+        }
+
+        for s in 0..segments {
+            let i = base + 2 * s;
+            indices.push([i, i + 1, i + 3]);
+            indices.push([i, i + 3, i + 2]);
+        }
+    }
+
+    TriangleMesh::new(positions, indices, material)
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut world = HittableList::new();
+
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.35, 0.25, 0.15)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        mat_ground,
+    )));
+
+    // one mesh, shared by every instance
+    let mat_grass = MatType::Lambertian(Lambertian::new(Vec3::new(0.2, 0.5, 0.1)));
+    let tuft: Arc<dyn Hittable> = Arc::new(grass_tuft(7, 6, mat_grass));
+
+    let mut field = HittableList::new();
+    for _ in 0..5000 {
+        let x = rng.gen_range(-6.0..6.0);
+        let z = rng.gen_range(-6.0..3.0);
+        let height = rng.gen_range(0.3..0.7);
+        let transform = Transform::scale(Vec3::new(height, height, height))
+            .then(&Transform::rotate_y(rng.gen_range(0.0..360.0)))
+            .then(&Transform::translate(Vec3::new(x, 0., z)));
+        field.add(Arc::new(Instance::new(tuft.clone(), transform)));
+    }
+    world.add(Arc::new(BvhNode::new(&field, 0., 1.)));
+
+    // a tilted ellipsoid made from a unit sphere
+    let ball: Arc<dyn Hittable> = Arc::new(Sphere::new(
+        Vec3::new(0., 0., 0.),
+        1.,
+        MatType::Conductor(Conductor::gold(0.2)),
+    ));
+    let transform = Transform::scale(Vec3::new(1.2, 0.6, 0.6))
+        .then(&Transform::rotate_z(30.))
+        .then(&Transform::translate(Vec3::new(0., 0.9, 0.)));
+    world.add(Arc::new(Instance::new(ball, transform)));
+
+    let world = BvhNode::new(&world, 0., 1.);
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 2., 7.);
+    let lookat = Vec3::new(0., 0.5, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        40.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("instancing.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
@@ -0,0 +1,137 @@
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable::Hittable;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Conductor, Lambertian, MatType};
+use rs_tracer::quad::Cuboid;
+use rs_tracer::sphere::Sphere;
+use rs_tracer::transform::{AnimatedTransform, Instance, Keyframe, Quaternion, Transform};
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::sync::Arc;
+
+// A wheel in the xy plane, centred on the origin: a hub with spokes and a
+// rim made of small blocks.
+fn wheel(radius: f64, spokes: usize, material: MatType) -> HittableList {
+    let mut parts = HittableList::new();
+
+    let hub: Arc<dyn Hittable> =
+        Arc::new(Sphere::new(Vec3::new(0., 0., 0.), 0.15, material.clone()));
+    parts.add(hub);
+
+    let spoke: Arc<dyn Hittable> = Arc::new(Cuboid::new(
+        Vec3::new(0., -0.03, -0.03),
+        Vec3::new(radius, 0.03, 0.03),
+        material.clone(),
+    ));
+    for i in 0..spokes {
+        let angle = 360. * i as f64 / spokes as f64;
+        parts.add(Arc::new(Instance::new(
+            spoke.clone(),
+            Transform::rotate_z(angle),
+        )));
+    }
+
+    let segment: Arc<dyn Hittable> = Arc::new(Cuboid::new(
+        Vec3::new(radius - 0.05, -0.14, -0.08),
+        Vec3::new(radius + 0.05, 0.14, 0.08),
+        material,
+    ));
+    for i in 0..24 {
+        let angle = 360. * i as f64 / 24.;
+        parts.add(Arc::new(Instance::new(
+            segment.clone(),
+            Transform::rotate_z(angle),
+        )));
+    }
+
+    parts
+}
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        mat_ground,
+    )));
+
+    // a wheel rolling to the right: a quarter turn per keyframe, since
+    // slerp always takes the shorter way round
+    let radius = 1.;
+    let turns = 0.5;
+    let distance = 2. * std::f64::consts::PI * radius * turns;
+    let keyframes = (0..=8)
+        .map(|i| {
+            let t = i as f64 / 8.;
+            Keyframe::new(t)
+                .translation(Vec3::new(-2. + t * distance, radius + 0.05, 0.))
+                .rotation(Quaternion::from_axis_angle(
+                    Vec3::new(0., 0., 1.),
+                    -360. * turns * t,
+                ))
+        })
+        .collect();
+
+    let mat_wheel = MatType::Conductor(Conductor::aluminium(0.3));
+    let wheel = Arc::new(BvhNode::new(&wheel(radius, 8, mat_wheel), 0., 1.));
+    world.add(Arc::new(Instance::animated(
+        wheel,
+        AnimatedTransform::new(keyframes),
+    )));
+
+    // a propeller spinning about z, facing the camera
+    let mat_blade = MatType::Lambertian(Lambertian::new(Vec3::new(0.8, 0.2, 0.1)));
+    let blade: Arc<dyn Hittable> = Arc::new(Cuboid::new(
+        Vec3::new(-0.9, -0.1, -0.02),
+        Vec3::new(0.9, 0.1, 0.02),
+        mat_blade,
+    ));
+    let spin = (0..=4)
+        .map(|i| {
+            Keyframe::new(i as f64 / 4.).rotation(Quaternion::from_axis_angle(
+                Vec3::new(0., 0., 1.),
+                90. * i as f64,
+            ))
+        })
+        .collect();
+    let propeller = Arc::new(Instance::animated(blade, AnimatedTransform::new(spin)));
+
+    // instances nest, so the spinning propeller can be placed like any
+    // other object
+    world.add(Arc::new(Instance::new(
+        propeller,
+        Transform::translate(Vec3::new(2.5, 2.5, -1.)),
+    )));
+
+    let world = BvhNode::new(&world, 0., 1.);
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 2., 9.);
+    let lookat = Vec3::new(0.5, 1.2, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    // the shutter is open for the first fifth of the animation
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        35.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    )
+    .with_shutter(0., 0.2);
+
+    let img = render(world, camera, config);
+
+    match img.save("keyframed_motion.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
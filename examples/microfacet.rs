@@ -0,0 +1,58 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Conductor, Lambertian, MatType, RoughDielectric};
+use rs_tracer::sphere::Sphere;
+use rs_tracer::texture::CheckerTexture;
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::sync::Arc;
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let checker =
+        CheckerTexture::from_colors(0.5, Vec3::new(0.2, 0.2, 0.2), Vec3::new(0.8, 0.8, 0.8));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        MatType::Lambertian(Lambertian::textured(Arc::new(checker))),
+    )));
+
+    // roughness increases from left to right
+    let materials = [
+        MatType::Conductor(Conductor::gold(0.05)),
+        MatType::Conductor(Conductor::copper(0.25)),
+        MatType::Conductor(Conductor::aluminium(0.5)),
+        MatType::RoughDielectric(RoughDielectric::new(1.5, 0.3)),
+    ];
+
+    for (i, material) in materials.into_iter().enumerate() {
+        let x = -3.3 + 2.2 * i as f64;
+        world.add(Arc::new(Sphere::new(Vec3::new(x, 1., 0.), 1., material)));
+    }
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 3., 12.);
+    let lookat = Vec3::new(0., 0.8, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        35.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("microfacet.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
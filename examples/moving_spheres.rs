@@ -1,20 +1,23 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rs_tracer::bvh::BvhNode;
 use rs_tracer::camera::Camera;
 use rs_tracer::hittable_list::HittableList;
 use rs_tracer::material::{Dielectric, Lambertian, MatType, Metal};
 use rs_tracer::sphere::Sphere;
 use rs_tracer::vec3::Vec3;
 use rs_tracer::{render, ImageConfig};
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() {
-    let mat_ground = MatType::Lambertian(Lambertian {
-        albedo: Vec3::new(0.8, 0.8, 0.),
-    });
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.8, 0.8, 0.)));
 
     let mut world = HittableList::new();
 
-    world.add(Rc::new(Sphere::new(
+    // a fixed seed places the small spheres the same way on every run
+    let mut rng = StdRng::seed_from_u64(1);
+
+    world.add(Arc::new(Sphere::new(
         Vec3::new(0., -1000., 0.),
         1000.,
         mat_ground,
@@ -22,34 +25,34 @@ fn main() {
 
     for a in -11..11 {
         for b in -11..11 {
-            let mat_val: f64 = rand::thread_rng().gen();
+            let mat_val: f64 = rng.gen();
             let center = Vec3 {
-                x: a as f64 + 0.9 * rand::thread_rng().gen::<f64>(),
+                x: a as f64 + 0.9 * rng.gen::<f64>(),
                 y: 0.2,
-                z: b as f64 + 0.9 * rand::thread_rng().gen::<f64>(),
+                z: b as f64 + 0.9 * rng.gen::<f64>(),
             };
 
             if (center - Vec3::new(4., 0.2, 0.)).len() > 0.9 {
                 if mat_val < 0.8 {
-                    let albedo = Vec3::random() * Vec3::random();
-                    let material = MatType::Lambertian(Lambertian { albedo });
+                    let albedo = Vec3::random(&mut rng) * Vec3::random(&mut rng);
+                    let material = MatType::Lambertian(Lambertian::new(albedo));
                     let target = center
                         + Vec3 {
                             x: 0.,
-                            y: rand::thread_rng().gen::<f64>() * 0.5,
+                            y: rng.gen::<f64>() * 0.5,
                             z: 0.,
                         };
 
-                    world.add(Rc::new(Sphere::new_moving(center, target, 0.2, material)));
+                    world.add(Arc::new(Sphere::new_moving(center, target, 0.2, material)));
                 } else if mat_val < 0.95 {
-                    let albedo = Vec3::random_range(0.5, 1.);
-                    let fuzz = rand::thread_rng().gen::<f64>() * 0.5;
-                    let material = MatType::Metal(Metal { albedo, fuzz });
-                    world.add(Rc::new(Sphere::new(center, 0.2, material)));
+                    let albedo = Vec3::random_range(&mut rng, 0.5, 1.);
+                    let fuzz = rng.gen::<f64>() * 0.5;
+                    let material = MatType::Metal(Metal::new(albedo, fuzz));
+                    world.add(Arc::new(Sphere::new(center, 0.2, material)));
                 } else {
                     let index_refraction = 1.5;
                     let material = MatType::Dielectric(Dielectric { index_refraction });
-                    world.add(Rc::new(Sphere::new(center, 0.2, material)));
+                    world.add(Arc::new(Sphere::new(center, 0.2, material)));
                 }
             }
         }
@@ -58,18 +61,13 @@ fn main() {
     let mat1 = MatType::Dielectric(Dielectric {
         index_refraction: 1.5,
     });
-    world.add(Rc::new(Sphere::new(Vec3::new(0., 1., 0.), 1., mat1)));
+    world.add(Arc::new(Sphere::new(Vec3::new(0., 1., 0.), 1., mat1)));
 
-    let mat2 = MatType::Lambertian(Lambertian {
-        albedo: Vec3::new(0.4, 0.2, 0.1),
-    });
-    world.add(Rc::new(Sphere::new(Vec3::new(-4., 1., 0.), 1., mat2)));
+    let mat2 = MatType::Lambertian(Lambertian::new(Vec3::new(0.4, 0.2, 0.1)));
+    world.add(Arc::new(Sphere::new(Vec3::new(-4., 1., 0.), 1., mat2)));
 
-    let mat3 = MatType::Metal(Metal {
-        albedo: Vec3::new(0.7, 0.6, 0.5),
-        fuzz: 0.0,
-    });
-    world.add(Rc::new(Sphere::new(Vec3::new(4., 1., 0.), 1., mat3)));
+    let mat3 = MatType::Metal(Metal::new(Vec3::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Arc::new(Sphere::new(Vec3::new(4., 1., 0.), 1., mat3)));
 
     let config = ImageConfig::default();
 
@@ -89,6 +87,8 @@ fn main() {
         dist_to_focus,
     );
 
+    let world = BvhNode::new(&world, 0., 1.);
+
     let img = render(world, camera, config); // use default camera config
 
     match img.save("moving_spheres.png") {
@@ -0,0 +1,70 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Lambertian, MatType};
+use rs_tracer::noise::WorleyFeature;
+use rs_tracer::sphere::Sphere;
+use rs_tracer::texture::{CellTexture, MarbleTexture, NoiseTexture, WoodTexture};
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::sync::Arc;
+
+fn main() {
+    // the same seed always gives the same patterns
+    let seed = 42;
+
+    let mut world = HittableList::new();
+
+    let ground = NoiseTexture::new(seed, 4.);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        MatType::Lambertian(Lambertian::textured(Arc::new(ground))),
+    )));
+
+    let marble = MarbleTexture::new(seed, 4.);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(-2.2, 1., 0.),
+        1.,
+        MatType::Lambertian(Lambertian::textured(Arc::new(marble))),
+    )));
+
+    let wood = WoodTexture::new(seed, 8.);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., 1., 0.),
+        1.,
+        MatType::Lambertian(Lambertian::textured(Arc::new(wood))),
+    )));
+
+    let mut cells = CellTexture::new(seed, 4., WorleyFeature::F2MinusF1);
+    cells.color = Vec3::new(0.9, 0.5, 0.2);
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(2.2, 1., 0.),
+        1.,
+        MatType::Lambertian(Lambertian::textured(Arc::new(cells))),
+    )));
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 3., 10.);
+    let lookat = Vec3::new(0., 0.8, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        35.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("noise_textures.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
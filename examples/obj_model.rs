@@ -0,0 +1,49 @@
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::obj::load_obj;
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::env;
+use std::process;
+
+fn main() {
+    // load the model given on the command line, or the bundled one
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| format!("{}/examples/models/shapes.obj", env!("CARGO_MANIFEST_DIR")));
+
+    let mut world = HittableList::new();
+
+    if let Err(e) = load_obj(&path, &mut world) {
+        eprintln!("Error loading model: {}", e);
+        process::exit(1);
+    }
+
+    let world = BvhNode::new(&world, 0., 1.);
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(4., 3., 6.);
+    let lookat = Vec3::new(0.5, 0.7, 0.5);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        35.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("obj_model.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
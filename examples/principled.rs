@@ -0,0 +1,80 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Lambertian, MatType, Principled};
+use rs_tracer::sphere::Sphere;
+use rs_tracer::texture::{CheckerTexture, NoiseTexture};
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::sync::Arc;
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let checker =
+        CheckerTexture::from_colors(0.5, Vec3::new(0.2, 0.2, 0.2), Vec3::new(0.8, 0.8, 0.8));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        MatType::Lambertian(Lambertian::textured(Arc::new(checker))),
+    )));
+
+    // red plastic with a clear coat
+    let plastic = Principled::new(Vec3::new(0.7, 0.05, 0.05))
+        .roughness(0.4)
+        .clearcoat(1.);
+
+    // brushed metal
+    let brushed = Principled::new(Vec3::new(0.9, 0.9, 0.9))
+        .metallic(1.)
+        .roughness(0.35)
+        .anisotropic(0.9);
+
+    // velvet-like cloth
+    let cloth = Principled::new(Vec3::new(0.1, 0.1, 0.4))
+        .roughness(1.)
+        .sheen(1.);
+
+    // frosted green glass
+    let glass = Principled::new(Vec3::new(0.6, 0.9, 0.7))
+        .transmission(1.)
+        .roughness(0.15);
+
+    // gold with roughness driven by a noise texture
+    let mut worn = Principled::new(Vec3::new(1., 0.78, 0.34)).metallic(1.);
+    worn.roughness = Arc::new(NoiseTexture::new(7, 4.));
+
+    let materials = [plastic, brushed, cloth, glass, worn];
+    for (i, material) in materials.into_iter().enumerate() {
+        let x = -4.4 + 2.2 * i as f64;
+        world.add(Arc::new(Sphere::new(
+            Vec3::new(x, 1., 0.),
+            1.,
+            MatType::Principled(material),
+        )));
+    }
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 3., 14.);
+    let lookat = Vec3::new(0., 0.8, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        35.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("principled.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
@@ -0,0 +1,71 @@
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::progressive::{CancelToken, ProgressiveRenderer};
+use rs_tracer::scene;
+use std::env;
+use std::io;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        format!(
+            "{}/examples/scenes/spheres.toml",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    });
+
+    let mut scene = match scene::load(&path) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error loading scene: {}", e);
+            process::exit(1);
+        }
+    };
+    scene.config.samples_per_pixel = 1000;
+
+    // pressing enter stops the render, keeping the passes finished so far
+    let cancel = CancelToken::new();
+    let stop = cancel.clone();
+    thread::spawn(move || {
+        let mut line = String::new();
+        if matches!(io::stdin().read_line(&mut line), Ok(n) if n > 0) {
+            stop.cancel();
+        }
+    });
+
+    println!("Rendering, press enter to stop early.");
+
+    let world = BvhNode::new(&scene.world, 0., 1.);
+    let result = ProgressiveRenderer::new()
+        .samples_per_pass(4)
+        .time_budget(Duration::from_secs(60))
+        .cancel_token(cancel)
+        .render(
+            world,
+            HittableList::new(),
+            scene.camera,
+            scene.config,
+            |film, pass| {
+                // the preview gets better with every pass
+                if let Err(e) = film.save("progressive.png") {
+                    eprintln!("Error writing file: {}", e);
+                }
+                println!(
+                    "pass {}: {}/{} spp after {:.1}s",
+                    pass.index + 1,
+                    pass.samples_per_pixel,
+                    pass.target_samples_per_pixel,
+                    pass.elapsed.as_secs_f64()
+                );
+            },
+        );
+
+    println!(
+        "{:?} with {} spp after {:.1}s.",
+        result.stop_reason,
+        result.samples_per_pixel,
+        result.elapsed.as_secs_f64()
+    );
+}
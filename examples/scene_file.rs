@@ -0,0 +1,31 @@
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::render;
+use rs_tracer::scene;
+use std::env;
+use std::process;
+
+fn main() {
+    // load the scene given on the command line, or the bundled one
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        format!(
+            "{}/examples/scenes/spheres.toml",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    });
+
+    let scene = match scene::load(&path) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error loading scene: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let world = BvhNode::new(&scene.world, 0., 1.);
+    let img = render(world, scene.camera, scene.config);
+
+    match img.save("scene_file.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
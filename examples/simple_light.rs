@@ -0,0 +1,59 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::color::Background;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{DiffuseLight, Lambertian, MatType};
+use rs_tracer::sphere::Sphere;
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render_with_lights, ImageConfig};
+use std::sync::Arc;
+
+fn main() {
+    // create a scene lit only by an emissive sphere
+    let mut world = HittableList::new();
+
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        mat_ground,
+    )));
+
+    let mat_ball = MatType::Lambertian(Lambertian::new(Vec3::new(0.8, 0.3, 0.3)));
+    world.add(Arc::new(Sphere::new(Vec3::new(0., 2., 0.), 2., mat_ball)));
+
+    let mat_light = MatType::DiffuseLight(DiffuseLight::new(Vec3::new(4., 4., 4.)));
+    let light = Arc::new(Sphere::new(Vec3::new(0., 7., 0.), 2., mat_light));
+    world.add(light.clone());
+
+    let mut lights = HittableList::new();
+    lights.add(light);
+
+    let config = ImageConfig {
+        samples_per_pixel: 100,
+        background: Background::Black,
+        ..Default::default()
+    };
+
+    let lookfrom = Vec3::new(26., 3., 6.);
+    let lookat = Vec3::new(0., 2., 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = 10.;
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        20.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render_with_lights(world, lights, camera, config);
+
+    match img.save("simple_light.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
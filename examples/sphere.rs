@@ -4,16 +4,14 @@ use rs_tracer::material::{Lambertian, MatType};
 use rs_tracer::sphere::Sphere;
 use rs_tracer::vec3::Vec3;
 use rs_tracer::{render, ImageConfig};
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() {
     // create a scene
     let mut world = HittableList::new();
 
-    let mat_cent = MatType::Lambertian(Lambertian {
-        albedo: Vec3::new(0.1, 0.2, 0.5),
-    });
-    world.add(Rc::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5, mat_cent)));
+    let mat_cent = MatType::Lambertian(Lambertian::new(Vec3::new(0.1, 0.2, 0.5)));
+    world.add(Arc::new(Sphere::new(Vec3::new(0., 0., -1.), 0.5, mat_cent)));
 
     let config = ImageConfig::default();
 
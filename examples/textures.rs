@@ -0,0 +1,68 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Lambertian, MatType, Metal};
+use rs_tracer::sphere::Sphere;
+use rs_tracer::texture::{CheckerTexture, ImageTexture};
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::sync::Arc;
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.5,
+        Vec3::new(0.2, 0.3, 0.1),
+        Vec3::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        MatType::Lambertian(Lambertian::textured(checker)),
+    )));
+
+    // wrap one of the README renders around a sphere
+    let path = format!("{}/imgs/dof.png", env!("CARGO_MANIFEST_DIR"));
+    let picture = match ImageTexture::load(&path) {
+        Ok(t) => Arc::new(t),
+        Err(e) => {
+            eprintln!("Error loading texture {}: {}", path, e);
+            return;
+        }
+    };
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., 1., 0.),
+        1.,
+        MatType::Lambertian(Lambertian::textured(picture.clone())),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(2.2, 0.7, 0.5),
+        0.7,
+        MatType::Metal(Metal::textured(picture, 0.2)),
+    )));
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 2., 8.);
+    let lookat = Vec3::new(0.5, 0.8, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("textures.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
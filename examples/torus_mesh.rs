@@ -0,0 +1,82 @@
+use rs_tracer::camera::Camera;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{Lambertian, MatType, Metal};
+use rs_tracer::mesh::TriangleMesh;
+use rs_tracer::sphere::Sphere;
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render, ImageConfig};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Tessellates a torus lying in the xz plane into an indexed mesh.
+fn torus(major: f64, minor: f64, rings: usize, sides: usize, material: MatType) -> TriangleMesh {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    for i in 0..rings {
+        let phi = 2. * PI * i as f64 / rings as f64;
+        for j in 0..sides {
+            let theta = 2. * PI * j as f64 / sides as f64;
+            let r = major + minor * theta.cos();
+            positions.push(Vec3::new(r * phi.cos(), minor * theta.sin(), r * phi.sin()));
+            uvs.push((i as f64 / rings as f64, j as f64 / sides as f64));
+        }
+    }
+
+    for i in 0..rings {
+        for j in 0..sides {
+            let a = i * sides + j;
+            let b = ((i + 1) % rings) * sides + j;
+            let c = ((i + 1) % rings) * sides + (j + 1) % sides;
+            let d = i * sides + (j + 1) % sides;
+            indices.push([a, b, c]);
+            indices.push([a, c, d]);
+        }
+    }
+
+    TriangleMesh::new(positions, indices, material)
+        .with_uvs(uvs)
+        .with_smooth_normals()
+}
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000.4, 0.),
+        1000.,
+        mat_ground,
+    )));
+
+    let mat_torus = MatType::Metal(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.1));
+    let mesh = torus(1., 0.4, 96, 48, mat_torus);
+    println!("{} triangles, BVH: {}", mesh.len(), mesh.bvh_stats());
+    world.add(Arc::new(mesh));
+
+    let config = ImageConfig::default();
+
+    let lookfrom = Vec3::new(0., 3., 5.);
+    let lookat = Vec3::new(0., 0., 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render(world, camera, config);
+
+    match img.save("torus_mesh.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
@@ -0,0 +1,119 @@
+use rs_tracer::aabb::Aabb;
+use rs_tracer::camera::Camera;
+use rs_tracer::color::Background;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::material::{DiffuseLight, HenyeyGreenstein, Lambertian, MatType};
+use rs_tracer::medium::{DensityField, HeterogeneousMedium, Media, NoiseDensity, VoxelGrid};
+use rs_tracer::sphere::Sphere;
+use rs_tracer::vec3::Vec3;
+use rs_tracer::{render_with_lights, ImageConfig};
+use std::sync::Arc;
+
+// noise that fades out towards the edge of a sphere, so the cloud does not
+// show the shape of its bounding box
+struct Puff {
+    noise: NoiseDensity,
+    center: Vec3,
+    radius: f64,
+}
+
+impl DensityField for Puff {
+    fn density(&self, p: Vec3) -> f64 {
+        let falloff = 1. - (p - self.center).len() / self.radius;
+        self.noise.density(p) * falloff.clamp(0., 1.)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.noise.max_density()
+    }
+}
+
+fn main() {
+    let mut world = HittableList::new();
+
+    let mat_ground = MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0., -1000., 0.),
+        1000.,
+        mat_ground,
+    )));
+
+    let mat_light = MatType::DiffuseLight(DiffuseLight::new(Vec3::new(10., 10., 10.)));
+    let light = Arc::new(Sphere::new(Vec3::new(-2., 8., 4.), 1.5, mat_light));
+    world.add(light.clone());
+
+    let mut lights = HittableList::new();
+    lights.add(light);
+
+    // forward scattering cloud
+    let center = Vec3::new(-1.6, 1.6, 0.);
+    let cloud_bounds = Aabb::new(
+        center - Vec3::new(1.5, 1.5, 1.5),
+        center + Vec3::new(1.5, 1.5, 1.5),
+    );
+    let puff = Puff {
+        noise: NoiseDensity::new(11, 1.5, 3.),
+        center,
+        radius: 1.5,
+    };
+    let cloud = HeterogeneousMedium::new(cloud_bounds, Arc::new(puff))
+        .scattering(4.)
+        .phase_function(MatType::HenyeyGreenstein(HenyeyGreenstein::new(
+            0.6,
+            Vec3::new(0.95, 0.95, 0.95),
+        )));
+
+    // glowing fireball from a voxel grid, which can also be loaded from a
+    // raw volume file given on the command line
+    let fire_bounds = Aabb::new(Vec3::new(0.6, 0., -1.2), Vec3::new(3., 2.4, 1.2));
+    let fire_center = Vec3::new(1.8, 1.2, 0.);
+    let grid = match std::env::args().nth(1) {
+        Some(path) => match VoxelGrid::load(&path, fire_bounds) {
+            Ok(grid) => grid,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                return;
+            }
+        },
+        None => VoxelGrid::from_fn([32, 32, 32], fire_bounds, |p| {
+            (1. - (p - fire_center).len() / 1.2).max(0.) * 2.
+        }),
+    };
+    let fire = HeterogeneousMedium::new(fire_bounds, Arc::new(grid))
+        .absorption(2.)
+        .scattering(1.)
+        .emission(Vec3::new(4., 1.2, 0.2));
+
+    let config = ImageConfig {
+        samples_per_pixel: 100,
+        background: Background::Solid(Vec3::new(0.02, 0.02, 0.05)),
+        media: Media {
+            volumes: vec![Arc::new(cloud), Arc::new(fire)],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let lookfrom = Vec3::new(0., 3., 12.);
+    let lookat = Vec3::new(0., 1.2, 0.);
+    let vup = Vec3::new(0., 1., 0.);
+    let aperture = 0.;
+    let dist_to_focus = (lookfrom - lookat).len();
+
+    let camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.,
+        config.aspect_ratio,
+        aperture,
+        dist_to_focus,
+    );
+
+    let img = render_with_lights(world, lights, camera, config);
+
+    match img.save("volumes.png") {
+        Err(e) => eprintln!("Error writing file: {}", e),
+        Ok(_) => println!("Done."),
+    };
+}
@@ -0,0 +1,101 @@
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+#[derive(Default, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_range(r, t_min, t_max).is_some()
+    }
+
+    /// Part of `[t_min, t_max]` for which the ray is inside the box.
+    pub fn hit_range(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        for a in 0..3 {
+            // Slab test, IEEE division handles axis-parallel rays.
+            let inv_d = 1. / r.dir[a];
+            let mut t0 = (self.min[a] - r.orig[a]) * inv_d;
+            let mut t1 = (self.max[a] - r.orig[a]) * inv_d;
+
+            if inv_d < 0. {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+
+    /// Smallest box containing both points, in any order.
+    pub fn from_points(a: Vec3, b: Vec3) -> Self {
+        Aabb::new(
+            Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        )
+    }
+
+    /// Widens any side thinner than `delta`, so flat shapes still have a
+    /// volume the slab test can hit.
+    pub fn pad(&self, delta: f64) -> Self {
+        let mut padded = *self;
+
+        for a in 0..3 {
+            if padded.max[a] - padded.min[a] < delta {
+                padded.min[a] -= delta / 2.;
+                padded.max[a] += delta / 2.;
+            }
+        }
+
+        padded
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2. * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Index of the axis along which the box is widest.
+    pub fn longest_axis(&self) -> u32 {
+        let extent = self.max - self.min;
+
+        if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+pub fn surrounding_box(box0: Aabb, box1: Aabb) -> Aabb {
+    let small = Vec3::new(
+        box0.min.x.min(box1.min.x),
+        box0.min.y.min(box1.min.y),
+        box0.min.z.min(box1.min.z),
+    );
+    let big = Vec3::new(
+        box0.max.x.max(box1.max.x),
+        box0.max.y.max(box1.max.y),
+        box0.max.z.max(box1.max.z),
+    );
+
+    Aabb::new(small, big)
+}
@@ -0,0 +1,309 @@
+use crate::camera::Camera;
+use crate::film::Film;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::material::luminance;
+use crate::sample_pixel;
+use crate::vec3::Vec3;
+use crate::ImageConfig;
+use image::{Rgb, RgbImage};
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::prelude::ParallelSliceMut;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Pixels darker than this are judged against it instead of their own mean, so
+// that nearly black pixels can converge.
+const MIN_MEAN: f64 = 0.01;
+
+/// Keeps sampling each pixel until its estimate has converged, so the
+/// samples go where the image is noisy. The `samples_per_pixel` of the image
+/// config becomes the cap for each pixel.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampler {
+    /// Samples every pixel gets before it may be judged converged.
+    pub min_samples: u32,
+    /// Converged once the standard error of the mean luminance falls below
+    /// this fraction of the mean.
+    pub relative_error: f64,
+    /// Samples taken between convergence checks.
+    pub batch_size: u32,
+}
+
+impl Default for AdaptiveSampler {
+    fn default() -> Self {
+        AdaptiveSampler {
+            min_samples: 16,
+            relative_error: 0.02,
+            batch_size: 8,
+        }
+    }
+}
+
+/// Running mean and variance with Welford's algorithm. The variance is only
+/// tracked for the luminance.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelEstimate {
+    pub samples: u32,
+    pub mean: Vec3,
+    mean_luminance: f64,
+    m2: f64,
+}
+
+impl PixelEstimate {
+    pub fn add(&mut self, sample: Vec3) {
+        self.samples += 1;
+        let n = self.samples as f64;
+
+        self.mean += (sample - self.mean) / n;
+
+        let y = luminance(sample);
+        let delta = y - self.mean_luminance;
+        self.mean_luminance += delta / n;
+        self.m2 += delta * (y - self.mean_luminance);
+    }
+
+    /// Sample variance of the luminance.
+    pub fn variance(&self) -> f64 {
+        if self.samples < 2 {
+            return 0.;
+        }
+
+        self.m2 / (self.samples - 1) as f64
+    }
+
+    /// Standard error of the mean luminance relative to the mean.
+    pub fn relative_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+
+        (self.variance() / self.samples as f64).sqrt() / self.mean_luminance.max(MIN_MEAN)
+    }
+}
+
+pub struct AdaptiveResult {
+    pub film: Film,
+    /// Samples taken for every pixel, row by row from the top.
+    pub sample_counts: Vec<u32>,
+    /// The cap every pixel was sampled up to.
+    pub max_samples: u32,
+}
+
+impl AdaptiveResult {
+    pub fn average_samples(&self) -> f64 {
+        let total: u64 = self.sample_counts.iter().map(|&n| n as u64).sum();
+        total as f64 / self.sample_counts.len().max(1) as f64
+    }
+
+    /// Sample counts as an image, from black for no samples through red and
+    /// yellow to white for `max_samples`.
+    pub fn heatmap(&self) -> RgbImage {
+        RgbImage::from_fn(self.film.width, self.film.height, |x, y| {
+            let n = self.sample_counts[y as usize * self.film.width as usize + x as usize];
+            let t = 3. * n as f64 / self.max_samples.max(1) as f64;
+
+            let channel = |c: f64| (255. * c.clamp(0., 1.)) as u8;
+            Rgb([channel(t), channel(t - 1.), channel(t - 2.)])
+        })
+    }
+}
+
+impl AdaptiveSampler {
+    pub fn new() -> Self {
+        AdaptiveSampler::default()
+    }
+
+    pub fn min_samples(mut self, min_samples: u32) -> Self {
+        self.min_samples = min_samples.max(2);
+        self
+    }
+
+    pub fn relative_error(mut self, relative_error: f64) -> Self {
+        self.relative_error = relative_error;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Like `render_with_lights`, with a varying number of samples per pixel.
+    pub fn render(
+        &self,
+        world: impl Hittable,
+        lights: HittableList,
+        camera: Camera,
+        config: ImageConfig,
+    ) -> AdaptiveResult {
+        self.render_with_progress(world, lights, camera, config, |_, _| {})
+    }
+
+    /// Like `render`, calling `progress(rows_done, rows)` whenever a row of
+    /// pixels has converged, as `render_with_progress` does.
+    pub fn render_with_progress(
+        &self,
+        world: impl Hittable,
+        lights: HittableList,
+        camera: Camera,
+        config: ImageConfig,
+        progress: impl Fn(u32, u32) + Sync,
+    ) -> AdaptiveResult {
+        let rows_done = AtomicU32::new(0);
+
+        let mut film = Film::new(config.width, config.height);
+        film.tone_mapping = config.tone_mapping;
+
+        let mut estimates = vec![PixelEstimate::default(); film.pixels().len()];
+
+        estimates
+            .par_chunks_exact_mut(config.width as usize)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, estimate) in row.iter_mut().enumerate() {
+                    self.sample(estimate, config.samples_per_pixel, |s| {
+                        sample_pixel(
+                            x as u32,
+                            y as u32,
+                            s..s + 1,
+                            &world,
+                            &lights,
+                            &camera,
+                            &config,
+                        )
+                    });
+                }
+
+                progress(rows_done.fetch_add(1, Ordering::Relaxed) + 1, config.height);
+            });
+
+        for (pixel, estimate) in film.pixels_mut().iter_mut().zip(estimates.iter()) {
+            *pixel = estimate.mean;
+        }
+
+        AdaptiveResult {
+            film,
+            sample_counts: estimates.iter().map(|e| e.samples).collect(),
+            max_samples: config.samples_per_pixel,
+        }
+    }
+
+    // Adds samples drawn from `trace`, which gets the index of the sample, to
+    // `estimate` until it converges or holds `max_samples`.
+    fn sample(&self, estimate: &mut PixelEstimate, max_samples: u32, trace: impl Fn(u32) -> Vec3) {
+        let min_samples = self.min_samples.max(2).min(max_samples);
+        let batch_size = self.batch_size.max(1);
+
+        while estimate.samples < min_samples {
+            estimate.add(trace(estimate.samples));
+        }
+
+        while estimate.samples < max_samples && estimate.relative_error() > self.relative_error {
+            let batch = batch_size.min(max_samples - estimate.samples);
+            for _ in 0..batch {
+                estimate.add(trace(estimate.samples));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Background;
+    use std::sync::Mutex;
+
+    fn flat_scene(width: u32, height: u32) -> (Camera, ImageConfig) {
+        let config = ImageConfig {
+            width,
+            height,
+            samples_per_pixel: 256,
+            background: Background::Solid(Vec3::new(0.25, 0.5, 0.75)),
+            ..ImageConfig::default()
+        };
+        let camera = Camera::new(
+            Vec3::new(0., 0., 0.),
+            Vec3::new(0., 0., -1.),
+            Vec3::new(0., 1., 0.),
+            60.,
+            width as f64 / height as f64,
+            0.,
+            1.,
+        );
+        (camera, config)
+    }
+
+    #[test]
+    fn flat_image_stops_at_min_samples() {
+        let (camera, config) = flat_scene(8, 6);
+        let result = AdaptiveSampler::new().min_samples(12).render(
+            HittableList::new(),
+            HittableList::new(),
+            camera,
+            config,
+        );
+
+        assert!(result.sample_counts.iter().all(|&n| n == 12));
+        assert_eq!(result.average_samples(), 12.);
+        assert_eq!(result.max_samples, 256);
+        for c in result.film.pixels() {
+            assert!((*c - Vec3::new(0.25, 0.5, 0.75)).near_zero());
+        }
+    }
+
+    #[test]
+    fn noisy_pixel_is_sampled_up_to_the_cap() {
+        let sampler = AdaptiveSampler::new().min_samples(4).batch_size(5);
+        let mut estimate = PixelEstimate::default();
+        let alternating = |s: u32| Vec3::new(1., 1., 1.) * (s % 2) as f64;
+
+        sampler.sample(&mut estimate, 33, alternating);
+        assert_eq!(estimate.samples, 33);
+        assert!(estimate.relative_error() > sampler.relative_error);
+    }
+
+    #[test]
+    fn welford_matches_two_pass_variance() {
+        let values = [0.5, 2., 0.25, 3.5, 1., 1.75];
+        let mut estimate = PixelEstimate::default();
+        for v in values {
+            estimate.add(Vec3::new(v, v, v));
+        }
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.);
+        assert!((estimate.mean.x - mean).abs() < 1e-12);
+        assert!((estimate.variance() - variance).abs() < 1e-12);
+        assert!((estimate.relative_error() - (variance / n).sqrt() / mean).abs() < 1e-12);
+    }
+
+    #[test]
+    fn progress_counts_every_row() {
+        let (camera, config) = flat_scene(4, 7);
+        let calls = Mutex::new(Vec::new());
+        AdaptiveSampler::new().render_with_progress(
+            HittableList::new(),
+            HittableList::new(),
+            camera,
+            config,
+            |done, rows| calls.lock().unwrap().push((done, rows)),
+        );
+
+        let mut calls = calls.into_inner().unwrap();
+        calls.sort();
+        assert_eq!(calls, (1..=7).map(|done| (done, 7)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn heatmap_runs_from_black_to_white() {
+        let result = AdaptiveResult {
+            film: Film::new(2, 1),
+            sample_counts: vec![0, 64],
+            max_samples: 64,
+        };
+        let heatmap = result.heatmap();
+        assert_eq!(heatmap.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(heatmap.get_pixel(1, 0).0, [255, 255, 255]);
+    }
+}
@@ -0,0 +1,616 @@
+use crate::aabb::{surrounding_box, Aabb};
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::fmt;
+use std::sync::Arc;
+
+/// How the builder partitions primitives at each interior node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMethod {
+    /// Split at the midpoint of the centroid bounds along the widest axis.
+    Middle,
+    /// Split into two halves with the same number of primitives.
+    EqualCounts,
+    /// Binned surface area heuristic.
+    Sah,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BvhBuilder {
+    pub split_method: SplitMethod,
+    pub max_leaf_size: usize,
+    pub sah_bins: usize,
+}
+
+impl Default for BvhBuilder {
+    fn default() -> Self {
+        BvhBuilder {
+            split_method: SplitMethod::Sah,
+            max_leaf_size: 4,
+            sah_bins: 12,
+        }
+    }
+}
+
+impl BvhBuilder {
+    pub fn new() -> Self {
+        BvhBuilder::default()
+    }
+
+    pub fn split_method(mut self, split_method: SplitMethod) -> Self {
+        self.split_method = split_method;
+        self
+    }
+
+    pub fn max_leaf_size(mut self, max_leaf_size: usize) -> Self {
+        self.max_leaf_size = max_leaf_size.max(1);
+        self
+    }
+
+    pub fn sah_bins(mut self, sah_bins: usize) -> Self {
+        self.sah_bins = sah_bins.max(2);
+        self
+    }
+
+    /// Builds a hierarchy over every object in `list`, bounding each one over
+    /// the shutter interval `[time0, time1]`.
+    pub fn build(&self, list: &HittableList, time0: f64, time1: f64) -> BvhNode {
+        let boxes: Vec<Aabb> = list
+            .objects
+            .iter()
+            .map(|obj| {
+                let mut bbox = Aabb::default();
+                if !obj.bounding_box(time0, time1, &mut bbox) {
+                    panic!("No bounding box in BvhNode constructor!");
+                }
+                bbox
+            })
+            .collect();
+
+        let bvh = Bvh::build(&boxes, self);
+        let objects = bvh
+            .indices
+            .iter()
+            .map(|&i| list.objects[i].clone())
+            .collect();
+
+        BvhNode { objects, bvh }
+    }
+}
+
+/// Summary of a built hierarchy, used to compare split strategies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub primitive_count: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    /// Expected traversal cost of the tree under the surface area heuristic.
+    pub sah_cost: f64,
+}
+
+impl BvhStats {
+    pub fn avg_leaf_size(&self) -> f64 {
+        if self.leaf_count == 0 {
+            return 0.;
+        }
+
+        self.primitive_count as f64 / self.leaf_count as f64
+    }
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves, depth {}, {}-{} prims/leaf (avg {:.2}), SAH cost {:.2}",
+            self.node_count,
+            self.leaf_count,
+            self.max_depth,
+            self.min_leaf_size,
+            self.max_leaf_size,
+            self.avg_leaf_size(),
+            self.sah_cost
+        )
+    }
+}
+
+// Relative cost of a box test compared to a primitive intersection.
+const TRAVERSAL_COST: f64 = 0.125;
+
+const MAX_UNBALANCED_DEPTH: usize = 32;
+const STACK_SIZE: usize = 64;
+
+#[derive(Clone, Copy)]
+struct FlatNode {
+    bbox: Aabb,
+    // Leaves: first primitive. Interior nodes: index of the right child, the
+    // left child always directly follows its parent.
+    offset: usize,
+    // Zero for interior nodes.
+    count: usize,
+    axis: u32,
+}
+
+#[derive(Clone, Copy)]
+struct BuildPrim {
+    index: usize,
+    bbox: Aabb,
+    centroid: Vec3,
+}
+
+/// Flattened hierarchy over primitive indices. Owners keep the primitives and
+/// resolve the indices handed to them during traversal.
+pub struct Bvh {
+    nodes: Vec<FlatNode>,
+    indices: Vec<usize>,
+    stats: BvhStats,
+}
+
+impl Bvh {
+    pub fn build(boxes: &[Aabb], builder: &BvhBuilder) -> Self {
+        let mut prims: Vec<BuildPrim> = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, bbox)| BuildPrim {
+                index,
+                bbox: *bbox,
+                centroid: bbox.centroid(),
+            })
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            indices: Vec::with_capacity(boxes.len()),
+            stats: BvhStats {
+                min_leaf_size: usize::MAX,
+                ..BvhStats::default()
+            },
+        };
+
+        if !prims.is_empty() {
+            bvh.build_recursive(&mut prims, builder, 1);
+            bvh.stats.sah_cost = bvh.sah_cost();
+        } else {
+            bvh.stats.min_leaf_size = 0;
+        }
+
+        bvh
+    }
+
+    fn build_recursive(&mut self, prims: &mut [BuildPrim], builder: &BvhBuilder, depth: usize) {
+        let bbox = prims
+            .iter()
+            .map(|p| p.bbox)
+            .reduce(surrounding_box)
+            .unwrap();
+        let node_index = self.nodes.len();
+        self.nodes.push(FlatNode {
+            bbox,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+        self.stats.node_count += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+
+        let centroid_bounds = prims
+            .iter()
+            .map(|p| Aabb::new(p.centroid, p.centroid))
+            .reduce(surrounding_box)
+            .unwrap();
+        let axis = centroid_bounds.longest_axis();
+
+        // Coincident centroids cannot be separated by any plane.
+        let degenerate = centroid_bounds.max[axis] <= centroid_bounds.min[axis];
+
+        // Past this depth fall back to median splits, which bounds the tree
+        // depth and therefore the traversal stack.
+        let split_method = if depth > MAX_UNBALANCED_DEPTH {
+            SplitMethod::EqualCounts
+        } else {
+            builder.split_method
+        };
+
+        // The SAH decides for itself whether small nodes are worth splitting.
+        let small = prims.len() <= builder.max_leaf_size && split_method != SplitMethod::Sah;
+
+        let mid = if prims.len() == 1 || degenerate || small {
+            None
+        } else {
+            match split_method {
+                SplitMethod::Middle => split_middle(prims, axis, &centroid_bounds),
+                SplitMethod::EqualCounts => Some(split_equal_counts(prims, axis)),
+                SplitMethod::Sah => split_sah(prims, axis, &bbox, &centroid_bounds, builder),
+            }
+        };
+
+        let mid = match mid {
+            Some(mid) => mid,
+            None if prims.len() > builder.max_leaf_size && !degenerate => {
+                split_equal_counts(prims, axis)
+            }
+            None => return self.make_leaf(node_index, prims),
+        };
+
+        let (left, right) = prims.split_at_mut(mid);
+        self.build_recursive(left, builder, depth + 1);
+        let right_index = self.nodes.len();
+        self.build_recursive(right, builder, depth + 1);
+
+        self.nodes[node_index].offset = right_index;
+        self.nodes[node_index].axis = axis;
+    }
+
+    fn make_leaf(&mut self, node_index: usize, prims: &[BuildPrim]) {
+        let node = &mut self.nodes[node_index];
+        node.offset = self.indices.len();
+        node.count = prims.len();
+        self.indices.extend(prims.iter().map(|p| p.index));
+
+        self.stats.leaf_count += 1;
+        self.stats.primitive_count += prims.len();
+        self.stats.min_leaf_size = self.stats.min_leaf_size.min(prims.len());
+        self.stats.max_leaf_size = self.stats.max_leaf_size.max(prims.len());
+    }
+
+    fn sah_cost(&self) -> f64 {
+        let root_area = self.nodes[0].bbox.surface_area();
+        if root_area <= 0. {
+            return 0.;
+        }
+
+        self.nodes
+            .iter()
+            .map(|node| {
+                let cost = if node.count > 0 {
+                    node.count as f64
+                } else {
+                    TRAVERSAL_COST
+                };
+                cost * node.bbox.surface_area() / root_area
+            })
+            .sum()
+    }
+
+    pub fn bbox(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bbox)
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    /// Primitive indices in leaf order.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Walks the nodes pierced by `r` front to back. `hit_prim` receives a
+    /// position into `indices()` and the current closest distance, and returns
+    /// the distance of a closer hit if there is one.
+    pub fn traverse(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut hit_prim: impl FnMut(usize, f64) -> Option<f64>,
+    ) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut closest_so_far = t_max;
+        let mut hit_anything = false;
+        let mut stack = [0usize; STACK_SIZE];
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node_index = stack[stack_len];
+            let node = &self.nodes[node_index];
+
+            if !node.bbox.hit(r, t_min, closest_so_far) {
+                continue;
+            }
+
+            if node.count > 0 {
+                for slot in node.offset..node.offset + node.count {
+                    if let Some(t) = hit_prim(slot, closest_so_far) {
+                        hit_anything = true;
+                        closest_so_far = t;
+                    }
+                }
+                continue;
+            }
+
+            // Push the far child first so the near one is visited next.
+            let (near, far) = if r.dir[node.axis] < 0. {
+                (node.offset, node_index + 1)
+            } else {
+                (node_index + 1, node.offset)
+            };
+            stack[stack_len] = far;
+            stack[stack_len + 1] = near;
+            stack_len += 2;
+        }
+
+        hit_anything
+    }
+}
+
+fn split_middle(prims: &mut [BuildPrim], axis: u32, centroid_bounds: &Aabb) -> Option<usize> {
+    let pivot = centroid_bounds.centroid()[axis];
+    let mid = partition(prims, |p| p.centroid[axis] < pivot);
+
+    if mid == 0 || mid == prims.len() {
+        None
+    } else {
+        Some(mid)
+    }
+}
+
+fn split_equal_counts(prims: &mut [BuildPrim], axis: u32) -> usize {
+    let mid = prims.len() / 2;
+    prims.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    mid
+}
+
+fn split_sah(
+    prims: &mut [BuildPrim],
+    axis: u32,
+    bbox: &Aabb,
+    centroid_bounds: &Aabb,
+    builder: &BvhBuilder,
+) -> Option<usize> {
+    let n_bins = builder.sah_bins;
+    let lo = centroid_bounds.min[axis];
+    let extent = centroid_bounds.max[axis] - lo;
+    let bin_of = |p: &BuildPrim| {
+        let b = (n_bins as f64 * (p.centroid[axis] - lo) / extent) as usize;
+        b.min(n_bins - 1)
+    };
+
+    let mut counts = vec![0usize; n_bins];
+    let mut bounds: Vec<Option<Aabb>> = vec![None; n_bins];
+    for p in prims.iter() {
+        let b = bin_of(p);
+        counts[b] += 1;
+        bounds[b] = Some(bounds[b].map_or(p.bbox, |bb| surrounding_box(bb, p.bbox)));
+    }
+
+    // Sweep from the right to get the area and count above each split plane.
+    let mut right_area = vec![0.; n_bins];
+    let mut right_count = vec![0usize; n_bins];
+    let mut acc: Option<Aabb> = None;
+    let mut count = 0;
+    for b in (1..n_bins).rev() {
+        count += counts[b];
+        acc = merge(acc, bounds[b]);
+        right_count[b] = count;
+        right_area[b] = acc.map_or(0., |bb| bb.surface_area());
+    }
+
+    let parent_area = bbox.surface_area();
+    let mut best: Option<(usize, f64)> = None;
+    let mut acc: Option<Aabb> = None;
+    let mut count = 0;
+    for b in 1..n_bins {
+        count += counts[b - 1];
+        acc = merge(acc, bounds[b - 1]);
+        if count == 0 || right_count[b] == 0 {
+            continue;
+        }
+
+        let left_area = acc.map_or(0., |bb| bb.surface_area());
+        let cost = TRAVERSAL_COST
+            + (count as f64 * left_area + right_count[b] as f64 * right_area[b]) / parent_area;
+        if best.is_none_or(|(_, c)| cost < c) {
+            best = Some((b, cost));
+        }
+    }
+
+    let (split_bin, cost) = best?;
+    if prims.len() <= builder.max_leaf_size && cost >= prims.len() as f64 {
+        return None;
+    }
+
+    Some(partition(prims, |p| bin_of(p) < split_bin))
+}
+
+fn merge(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(surrounding_box(a, b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+// In-place partition, returns the number of elements satisfying `pred`.
+fn partition(prims: &mut [BuildPrim], pred: impl Fn(&BuildPrim) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..prims.len() {
+        if pred(&prims[i]) {
+            prims.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+pub struct BvhNode {
+    objects: Vec<Arc<dyn Hittable>>,
+    bvh: Bvh,
+}
+
+impl BvhNode {
+    /// Builds a hierarchy over every object in `list` with the default SAH
+    /// builder. Boxes are taken over the shutter interval `[time0, time1]` so
+    /// moving objects stay enclosed.
+    pub fn new(list: &HittableList, time0: f64, time1: f64) -> Self {
+        BvhBuilder::default().build(list, time0, time1)
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.bvh.stats()
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        self.bvh.traverse(r, t_min, t_max, |slot, closest_so_far| {
+            if self.objects[slot].hit(r, t_min, closest_so_far, rec) {
+                Some(rec.t)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        match self.bvh.bbox() {
+            Some(bbox) => {
+                *output_box = bbox;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MatType;
+    use crate::sampler::Sampler;
+    use crate::sphere::Sphere;
+    use crate::INF;
+
+    fn random_point(sampler: &mut Sampler, extent: f64) -> Vec3 {
+        Vec3::new(
+            extent * (2. * sampler.next_f64() - 1.),
+            extent * (2. * sampler.next_f64() - 1.),
+            extent * (2. * sampler.next_f64() - 1.),
+        )
+    }
+
+    // Spheres of varying size, a quarter of them moving over the shutter.
+    fn random_scene(count: usize) -> HittableList {
+        let mut sampler = Sampler::new(11);
+        let mut list = HittableList::new();
+        for i in 0..count {
+            let center = random_point(&mut sampler, 10.);
+            let radius = 0.1 + 0.6 * sampler.next_f64();
+            let sphere = if i % 4 == 0 {
+                let target = center + random_point(&mut sampler, 1.);
+                Sphere::new_moving(center, target, radius, MatType::default())
+            } else {
+                Sphere::new(center, radius, MatType::default())
+            };
+            list.add(Arc::new(sphere));
+        }
+        list
+    }
+
+    // Compares the closest hit of `bvh` with a linear scan of `list`.
+    fn assert_same_hits(bvh: &impl Hittable, list: &HittableList) {
+        let mut sampler = Sampler::new(5);
+        let mut hits = 0;
+
+        for _ in 0..2000 {
+            let r = Ray {
+                orig: random_point(&mut sampler, 12.),
+                dir: Vec3::random_unit(&mut sampler),
+                time: sampler.next_f64(),
+            };
+
+            let mut expected = HitRecord::new();
+            let mut actual = HitRecord::new();
+            let hit = list.hit(&r, 0.001, INF, &mut expected);
+            assert_eq!(bvh.hit(&r, 0.001, INF, &mut actual), hit);
+            if hit {
+                assert_eq!(actual.t, expected.t);
+                hits += 1;
+            }
+        }
+
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn bvh_hits_match_linear_scan() {
+        let list = random_scene(300);
+        assert_same_hits(&BvhNode::new(&list, 0., 1.), &list);
+    }
+
+    #[test]
+    fn bvh_bounds_moving_objects_over_the_shutter() {
+        let mut list = HittableList::new();
+        list.add(Arc::new(Sphere::new_moving(
+            Vec3::new(0., 0., 0.),
+            Vec3::new(4., 0., 0.),
+            1.,
+            MatType::default(),
+        )));
+        let bvh = BvhNode::new(&list, 0., 1.);
+
+        let mut bbox = Aabb::default();
+        assert!(bvh.bounding_box(0., 1., &mut bbox));
+        assert!(bbox.min.x <= -1. && bbox.max.x >= 5.);
+
+        // Only the sphere at the end of the shutter is in the ray's way.
+        let r = Ray {
+            orig: Vec3::new(4., 0., 5.),
+            dir: Vec3::new(0., 0., -1.),
+            time: 1.,
+        };
+        let mut rec = HitRecord::new();
+        assert!(bvh.hit(&r, 0.001, INF, &mut rec));
+        assert!((rec.t - 4.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn every_split_method_finds_the_same_hits() {
+        let list = random_scene(300);
+        let methods = [
+            SplitMethod::Middle,
+            SplitMethod::EqualCounts,
+            SplitMethod::Sah,
+        ];
+
+        for method in methods {
+            for leaf_size in [1, 4, 8] {
+                let builder = BvhBuilder::new()
+                    .split_method(method)
+                    .max_leaf_size(leaf_size);
+                let bvh = builder.build(&list, 0., 1.);
+                assert_same_hits(&bvh, &list);
+
+                let stats = bvh.stats();
+                assert_eq!(stats.primitive_count, 300);
+                assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+                assert!(stats.max_leaf_size <= leaf_size);
+                assert!(stats.sah_cost > 0.);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_list_builds_an_empty_hierarchy() {
+        let bvh = BvhNode::new(&HittableList::new(), 0., 1.);
+        let mut bbox = Aabb::default();
+        assert!(!bvh.bounding_box(0., 1., &mut bbox));
+        assert_eq!(bvh.stats().primitive_count, 0);
+
+        let r = Ray {
+            orig: Vec3::new(0., 0., 0.),
+            dir: Vec3::new(0., 0., 1.),
+            time: 0.,
+        };
+        assert!(!bvh.hit(&r, 0.001, INF, &mut HitRecord::new()));
+    }
+}
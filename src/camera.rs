@@ -1,7 +1,7 @@
 use crate::deg_to_rad;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{cross, unit_vector, Vec3};
-use rand::Rng;
 
 pub struct Camera {
     pub origin: Vec3,
@@ -12,6 +12,17 @@ pub struct Camera {
     pub v: Vec3,
     pub w: Vec3,
     pub lens_radius: f64,
+    /// Rays are spread uniformly over the times the shutter is open.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    // Arguments of `new` that cannot be recovered exactly from the fields
+    // above, kept for describing the camera in a scene file.
+    pub(crate) lookat: Vec3,
+    pub(crate) vup: Vec3,
+    pub(crate) vfov: f64,
+    pub(crate) aspect_ratio: f64,
+    pub(crate) aperture: f64,
+    pub(crate) focus_dist: f64,
 }
 
 impl Camera {
@@ -48,11 +59,26 @@ impl Camera {
             v,
             w,
             lens_radius,
+            shutter_open: 0.,
+            shutter_close: 1.,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            aperture,
+            focus_dist,
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    /// Sets the interval the shutter is open for, `[0, 1)` by default.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut Sampler) -> Ray {
+        let rd = self.lens_radius * Vec3::random_in_unit_disk(sampler);
         let offset = self.u * rd.x + self.v * rd.y;
 
         Ray {
@@ -60,7 +86,33 @@ impl Camera {
             dir: self.lower_left_corner + s * self.horizontal + t * self.vertical
                 - self.origin
                 - offset,
-            time: rand::thread_rng().gen(),
+            time: self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.next_f64(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_times_fill_the_shutter_interval() {
+        let camera = Camera::new(
+            Vec3::new(0., 0., 0.),
+            Vec3::new(0., 0., -1.),
+            Vec3::new(0., 1., 0.),
+            90.,
+            1.,
+            0.,
+            1.,
+        )
+        .with_shutter(0.25, 0.75);
+
+        let mut sampler = Sampler::new(4);
+        let times: Vec<f64> = (0..1000)
+            .map(|_| camera.get_ray(0.5, 0.5, &mut sampler).time)
+            .collect();
+        assert!(times.iter().all(|t| (0.25..0.75).contains(t)));
+        assert!(times.iter().any(|&t| t < 0.3) && times.iter().any(|&t| t > 0.7));
+    }
+}
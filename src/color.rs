@@ -1,31 +1,184 @@
 use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::medium::{medium_hit, Media, MediumEvent};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{unit_vector, Vec3};
 
-pub fn ray_color(r: &Ray, world: &impl Hittable, depth: u32) -> Vec3 {
-    let mut rec = HitRecord::new();
+/// Radiance arriving along rays that leave the scene.
+#[derive(Clone, Copy)]
+pub enum Background {
+    Black,
+    Solid(Vec3),
+    /// Vertical blend from `bottom` (looking straight down) to `top`.
+    Gradient {
+        bottom: Vec3,
+        top: Vec3,
+    },
+}
 
-    if depth <= 0 {
-        return Vec3::new(0., 0., 0.);
+impl Background {
+    pub fn color(&self, r: &Ray) -> Vec3 {
+        match self {
+            Background::Black => Vec3::new(0., 0., 0.),
+            Background::Solid(c) => *c,
+            Background::Gradient { bottom, top } => {
+                let unit_dir = unit_vector(r.dir);
+                let t = 0.5 * (unit_dir.y + 1.);
+                (1. - t) * *bottom + t * *top
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Vec3::new(1., 1., 1.),
+            top: Vec3::new(0.5, 0.7, 1.),
+        }
     }
+}
+
+/// Estimates the radiance arriving along `r` by following a path of up to
+/// `depth` bounces. At every non-specular bounce the path also samples a point
+/// on `lights` directly, and weighs that sample against the BSDF-sampled
+/// direction with the power heuristic. Every emissive object in `world` that
+/// should be sampled directly must also be in `lights`. Paths can also
+/// collide with `media` on their way between surfaces. Every random decision
+/// along the path is drawn from `sampler`.
+pub fn ray_color(
+    r: &Ray,
+    world: &impl Hittable,
+    lights: &HittableList,
+    background: &Background,
+    media: &Media,
+    depth: u32,
+    sampler: &mut Sampler,
+) -> Vec3 {
+    let mut radiance = Vec3::new(0., 0., 0.);
+    let mut throughput = Vec3::new(1., 1., 1.);
+    let mut ray = Ray {
+        orig: r.orig,
+        dir: r.dir,
+        time: r.time,
+    };
+
+    // Origin and BSDF density of the last bounce, `None` after the camera or
+    // a specular bounce, where emission has to be counted in full.
+    let mut prev_bounce: Option<(Vec3, f64)> = None;
+
+    for _ in 0..depth {
+        let mut rec = HitRecord::new();
 
-    if world.hit(r, 0.001, crate::INF, &mut rec) {
-        let mut scattered = Ray::default();
-        let mut attenuation = Vec3::new(0., 0., 0.);
+        let hit = world.hit(&ray, 0.001, crate::INF, &mut rec);
+        let t_max = if hit { rec.t } else { crate::INF };
 
-        if rec
-            .material
-            .scatter(r, &rec, &mut attenuation, &mut scattered)
-        {
-            return attenuation * ray_color(&scattered, world, depth - 1);
+        // Colliding with a medium first replaces the surface hit. Otherwise
+        // the collision sampling already accounts for the transmittance up
+        // to the surface.
+        match media.sample_collision(&ray, t_max, sampler) {
+            Some(MediumEvent::Scatter { t, phase_function }) => {
+                medium_hit(&ray, t, phase_function, &mut rec);
+            }
+            Some(MediumEvent::Absorb { emission, .. }) => {
+                radiance += throughput * emission;
+                break;
+            }
+            None if !hit => {
+                radiance += throughput * background.color(&ray);
+                break;
+            }
+            None => {}
         }
 
+        let emitted = rec.material.emitted(rec.u, rec.v, rec.p);
+        if !emitted.near_zero() {
+            let weight = match prev_bounce {
+                Some((origin, bsdf_pdf)) if !lights.is_empty() => {
+                    power_heuristic(bsdf_pdf, lights.pdf_value(origin, ray.dir, ray.time))
+                }
+                _ => 1.,
+            };
+            radiance += weight * throughput * emitted;
+        }
+
+        let srec = match rec.material.scatter(&ray, &rec, sampler) {
+            Some(srec) => srec,
+            None => break,
+        };
+
+        if !srec.is_specular && !lights.is_empty() {
+            let wo = -unit_vector(ray.dir);
+            radiance +=
+                throughput * sample_light(world, lights, media, &rec, wo, ray.time, sampler);
+        }
+
+        prev_bounce = if srec.is_specular {
+            None
+        } else {
+            Some((rec.p, srec.pdf))
+        };
+
+        throughput = throughput * srec.throughput;
+        ray = srec.ray;
+    }
+
+    radiance
+}
+
+// Direct light through one shadow ray towards a point sampled on `lights`,
+// weighted for combination with BSDF sampling.
+fn sample_light(
+    world: &impl Hittable,
+    lights: &HittableList,
+    media: &Media,
+    rec: &HitRecord,
+    wo: Vec3,
+    time: f64,
+    sampler: &mut Sampler,
+) -> Vec3 {
+    let to_light = lights.random(rec.p, time, sampler);
+    let wi = unit_vector(to_light);
+
+    let light_pdf = lights.pdf_value(rec.p, to_light, time);
+    let f = rec.material.eval(rec, wi, wo);
+    if light_pdf <= 0. || f.near_zero() {
+        return Vec3::new(0., 0., 0.);
+    }
+
+    // Whatever the shadow ray hits first is what shines on the surface.
+    let shadow = Ray {
+        orig: rec.p,
+        dir: to_light,
+        time,
+    };
+    let mut light_rec = HitRecord::new();
+    if !world.hit(&shadow, 0.001, crate::INF, &mut light_rec) {
         return Vec3::new(0., 0., 0.);
     }
 
-    let unit_dir = unit_vector(r.dir);
-    let t = 0.5 * (unit_dir.y + 1.);
-    (1. - t) * Vec3::new(1., 1., 1.) + t * Vec3::new(0.5, 0.7, 1.)
+    let emitted = light_rec
+        .material
+        .emitted(light_rec.u, light_rec.v, light_rec.p);
+
+    let transmittance = media.transmittance(&shadow, 0., light_rec.t, sampler);
+    let bsdf_pdf = rec.material.pdf(rec, wi, wo);
+
+    power_heuristic(light_pdf, bsdf_pdf) * transmittance * f * emitted / light_pdf
+}
+
+/// Multiple importance sampling weight for a sample drawn with density `f`
+/// against an alternative strategy with density `g`.
+pub fn power_heuristic(f: f64, g: f64) -> f64 {
+    let f2 = f * f;
+    let g2 = g * g;
+
+    if f2 + g2 == 0. {
+        return 0.;
+    }
+
+    f2 / (f2 + g2)
 }
 
 pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
@@ -39,3 +192,121 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
 
     x
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::material::{DiffuseLight, Lambertian, MatType};
+    use crate::quad::Quad;
+    use crate::sphere::Sphere;
+    use crate::{render, render_with_lights, ImageConfig, PI};
+    use std::sync::Arc;
+
+    #[test]
+    fn mis_weights_for_one_light_sum_to_one() {
+        for (f, g) in [(1., 1.), (0.3, 2.5), (1e-6, 40.), (7., 0.), (0., 0.2)] {
+            let sum = power_heuristic(f, g) + power_heuristic(g, f);
+            assert!((sum - 1.).abs() < 1e-12);
+        }
+        assert_eq!(power_heuristic(0., 0.), 0.);
+    }
+
+    #[test]
+    fn sphere_light_samples_its_cone_uniformly() {
+        let light = Sphere::new(Vec3::new(0., 3., 0.), 1., MatType::default());
+        let origin = Vec3::new(0., 0., 0.);
+        let solid_angle = 2. * PI * (1. - (1. - 1. / 9f64).sqrt());
+        let mut sampler = Sampler::new(2);
+
+        for _ in 0..100 {
+            let dir = light.random(origin, 0., &mut sampler);
+            let pdf = light.pdf_value(origin, dir, 0.);
+            assert!((pdf * solid_angle - 1.).abs() < 1e-9);
+        }
+        assert_eq!(light.pdf_value(origin, Vec3::new(0., -1., 0.), 0.), 0.);
+    }
+
+    #[test]
+    fn quad_light_density_integrates_to_its_solid_angle() {
+        let light = Quad::new(
+            Vec3::new(-1., 2., -0.5),
+            Vec3::new(2., 0., 0.),
+            Vec3::new(0., 0., 1.),
+            MatType::default(),
+        );
+        let origin = Vec3::new(0., 0., 0.);
+        let (a, b, d) = (2f64, 1f64, 2f64);
+        let solid_angle =
+            4. * (a * b / ((a * a + 4. * d * d) * (b * b + 4. * d * d)).sqrt()).asin();
+
+        // The mean of 1 / pdf over directions drawn with that pdf.
+        let mut sampler = Sampler::new(3);
+        let n = 20000;
+        let estimate = (0..n)
+            .map(|_| 1. / light.pdf_value(origin, light.random(origin, 0., &mut sampler), 0.))
+            .sum::<f64>()
+            / n as f64;
+        assert!((estimate / solid_angle - 1.).abs() < 0.01);
+    }
+
+    // A diffuse floor under a small sphere light, with a black background so
+    // all light comes from the sphere.
+    fn lit_floor() -> (HittableList, HittableList, Camera, ImageConfig) {
+        let light: Arc<Sphere> = Arc::new(Sphere::new(
+            Vec3::new(0., 2., 0.),
+            0.3,
+            MatType::DiffuseLight(DiffuseLight::new(Vec3::new(10., 10., 10.))),
+        ));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Vec3::new(0., -1000., 0.),
+            1000.,
+            MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
+        )));
+        world.add(light.clone());
+        let mut lights = HittableList::new();
+        lights.add(light);
+
+        let config = ImageConfig {
+            width: 16,
+            height: 16,
+            samples_per_pixel: 256,
+            max_depth: 4,
+            background: Background::Black,
+            ..ImageConfig::default()
+        };
+        let camera = Camera::new(
+            Vec3::new(0., 1., 3.),
+            Vec3::new(0., 0., 0.),
+            Vec3::new(0., 1., 0.),
+            40.,
+            1.,
+            0.,
+            3.,
+        );
+        (world, lights, camera, config)
+    }
+
+    fn mean(film: &crate::film::Film) -> f64 {
+        film.pixels().iter().map(|c| c.x + c.y + c.z).sum::<f64>() / film.pixels().len() as f64
+    }
+
+    #[test]
+    fn light_sampling_converges_to_bsdf_sampling() {
+        let (world, _, camera, config) = lit_floor();
+        let bsdf_only = mean(&render(
+            world,
+            camera,
+            ImageConfig {
+                samples_per_pixel: 4096,
+                ..config
+            },
+        ));
+
+        let (world, lights, camera, config) = lit_floor();
+        let with_lights = mean(&render_with_lights(world, lights, camera, config));
+
+        assert!((with_lights / bsdf_only - 1.).abs() < 0.02);
+    }
+}
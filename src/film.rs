@@ -0,0 +1,226 @@
+use crate::tonemap::ToneMapping;
+use crate::vec3::Vec3;
+use image::codecs::hdr::HdrEncoder;
+use image::{ImageError, ImageResult, Rgb, Rgb32FImage, RgbImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Linear, unclamped radiance for every pixel, stored row by row from the top
+/// of the image. The data is only tone mapped on export, so `tone_mapping`
+/// can be changed to develop the same render differently.
+#[derive(Clone)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub tone_mapping: ToneMapping,
+    pixels: Vec<Vec3>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Self {
+        if width == 0 || height == 0 {
+            panic!("Film needs a width and height of at least one pixel!");
+        }
+
+        Film {
+            width,
+            height,
+            tone_mapping: ToneMapping::default(),
+            pixels: vec![Vec3::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Vec3) {
+        self.pixels[y as usize * self.width as usize + x as usize] = color;
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Vec3] {
+        &mut self.pixels
+    }
+
+    /// 8-bit image developed with the film's `tone_mapping`.
+    pub fn to_rgb_image(&self) -> RgbImage {
+        self.to_rgb_image_with(&self.tone_mapping)
+    }
+
+    pub fn to_rgb_image_with(&self, tone_mapping: &ToneMapping) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            Rgb(tone_mapping.to_rgb8(self.get(x, y)))
+        })
+    }
+
+    pub fn to_rgb32f_image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            let c = self.get(x, y);
+            Rgb([c.x as f32, c.y as f32, c.z as f32])
+        })
+    }
+
+    /// Saves the film in the format given by the file extension: `exr`, `hdr`
+    /// and `pfm` keep the linear data, anything else is written as an 8-bit
+    /// image.
+    pub fn save(&self, path: impl AsRef<Path>) -> ImageResult<()> {
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match ext.as_deref() {
+            Some("exr") => self.save_exr(path),
+            Some("hdr") => self.save_hdr(path),
+            Some("pfm") => self.save_pfm(path),
+            _ => self.to_rgb_image().save(path),
+        }
+    }
+
+    /// OpenEXR with 32-bit float channels.
+    pub fn save_exr(&self, path: impl AsRef<Path>) -> ImageResult<()> {
+        self.to_rgb32f_image()
+            .save_with_format(path, image::ImageFormat::OpenExr)
+    }
+
+    /// Radiance RGBE.
+    pub fn save_hdr(&self, path: impl AsRef<Path>) -> ImageResult<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let data: Vec<Rgb<f32>> = self
+            .pixels
+            .iter()
+            .map(|c| Rgb([c.x as f32, c.y as f32, c.z as f32]))
+            .collect();
+
+        HdrEncoder::new(file).encode(&data, self.width as usize, self.height as usize)
+    }
+
+    /// Portable float map, little-endian.
+    pub fn save_pfm(&self, path: impl AsRef<Path>) -> ImageResult<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        // A negative scale marks little-endian data, rows run bottom to top.
+        write!(file, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for row in self.pixels.chunks_exact(self.width as usize).rev() {
+            for c in row {
+                for v in [c.x, c.y, c.z] {
+                    file.write_all(&(v as f32).to_le_bytes())?;
+                }
+            }
+        }
+
+        file.flush().map_err(ImageError::IoError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::hdr::HdrDecoder;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Distinct values in every channel, with radiance well above 1.
+    fn test_film() -> Film {
+        let mut film = Film::new(5, 3);
+        for y in 0..3 {
+            for x in 0..5 {
+                let c = Vec3::new(x as f64 * 0.75, y as f64 * 4.5 + 0.125, 20. + x as f64);
+                film.set(x, y, c);
+            }
+        }
+        film
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rs-tracer-film-{}-{}", std::process::id(), name))
+    }
+
+    fn read_back(path: &Path) -> Rgb32FImage {
+        let image = image::open(path).unwrap().to_rgb32f();
+        fs::remove_file(path).unwrap();
+        image
+    }
+
+    fn assert_close(film: &Film, image: &Rgb32FImage, tolerance: f64) {
+        assert_eq!(image.dimensions(), (film.width, film.height));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let c = film.get(x, y);
+            for (expected, actual) in [c.x, c.y, c.z].into_iter().zip(pixel.0) {
+                assert!(
+                    (expected - actual as f64).abs() <= tolerance * expected.max(1e-3),
+                    "pixel ({}, {}): expected {}, got {}",
+                    x,
+                    y,
+                    expected,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exr_keeps_float_radiance() {
+        let film = test_film();
+        let path = temp_path("test.exr");
+        film.save(&path).unwrap();
+        assert_close(&film, &read_back(&path), 0.);
+    }
+
+    #[test]
+    fn hdr_keeps_radiance_to_rgbe_precision() {
+        let film = test_film();
+        let path = temp_path("test.hdr");
+        film.save(&path).unwrap();
+
+        let decoder = HdrDecoder::new(std::io::BufReader::new(File::open(&path).unwrap())).unwrap();
+        let metadata = decoder.metadata();
+        assert_eq!((metadata.width, metadata.height), (5, 3));
+        let pixels = decoder.read_image_hdr().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // RGBE shares one exponent per pixel, leaving 8 bits of mantissa for
+        // the brightest channel.
+        for (c, pixel) in film.pixels().iter().zip(pixels) {
+            let max = c.x.max(c.y).max(c.z);
+            for (expected, actual) in [c.x, c.y, c.z].into_iter().zip(pixel.0) {
+                assert!((expected - actual as f64).abs() <= max / 128.);
+            }
+        }
+    }
+
+    #[test]
+    fn pfm_is_little_endian_bottom_row_first() {
+        let film = test_film();
+        let path = temp_path("test.pfm");
+        film.save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let header = b"PF\n5 3\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let floats: Vec<f32> = bytes[header.len()..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats.len(), 5 * 3 * 3);
+
+        for (i, rgb) in floats.chunks_exact(3).enumerate() {
+            let (x, y) = (i as u32 % 5, 2 - i as u32 / 5);
+            let c = film.get(x, y);
+            assert_eq!(rgb, [c.x as f32, c.y as f32, c.z as f32]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_empty_film() {
+        Film::new(0, 4);
+    }
+}
@@ -1,25 +1,43 @@
+use crate::aabb::Aabb;
 use crate::material::MatType;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{dot, Vec3};
+use std::sync::LazyLock;
 
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub p: Vec3,
     pub norm: Vec3,
+    /// Direction of increasing `u` along the surface, orients anisotropic
+    /// materials. Not normalized, and zero where the surface has none.
+    pub tangent: Vec3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front: bool,
-    pub material: MatType,
+    pub material: &'a MatType,
 }
 
-impl HitRecord {
+// Placeholder material for records that have not hit anything yet.
+static DEFAULT_MATERIAL: LazyLock<MatType> = LazyLock::new(MatType::default);
+
+impl Default for HitRecord<'_> {
+    fn default() -> Self {
+        HitRecord::new()
+    }
+}
+
+impl HitRecord<'_> {
     pub fn new() -> Self {
         HitRecord {
             p: Vec3::new(0., 0., 0.),
             norm: Vec3::new(0., 0., 0.),
+            tangent: Vec3::new(0., 0., 0.),
             t: 0.,
+            u: 0.,
+            v: 0.,
             front: false,
-            material: MatType::Lambertian(crate::material::Lambertian {
-                albedo: Vec3::new(1., 1., 1.),
-            }),
+            material: &DEFAULT_MATERIAL,
         }
     }
 
@@ -33,6 +51,23 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+pub trait Hittable: Send + Sync {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool;
+
+    /// Box enclosing the object for every time in `[time0, time1]`, returns
+    /// false for unbounded objects.
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool;
+
+    /// Solid angle density, as seen from `origin` at `time`, with which
+    /// `random` picks `dir`. Only shapes that can be sampled as lights
+    /// override this.
+    fn pdf_value(&self, _origin: Vec3, _dir: Vec3, _time: f64) -> f64 {
+        0.
+    }
+
+    /// Vector from `origin` to a random point on the object, placed where it
+    /// is at `time`.
+    fn random(&self, _origin: Vec3, _time: f64, _sampler: &mut Sampler) -> Vec3 {
+        Vec3::new(1., 0., 0.)
+    }
 }
@@ -1,9 +1,19 @@
+use crate::aabb::{surrounding_box, Aabb};
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
-use std::rc::Rc;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+use rand::Rng;
+use std::sync::Arc;
 
 pub struct HittableList {
-    objects: Vec<Rc<dyn Hittable>>,
+    pub objects: Vec<Arc<dyn Hittable>>,
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        HittableList::new()
+    }
 }
 
 impl HittableList {
@@ -13,17 +23,21 @@ impl HittableList {
         }
     }
 
-    pub fn add(&mut self, obj: Rc<dyn Hittable>) {
+    pub fn add(&mut self, obj: Arc<dyn Hittable>) {
         self.objects.push(obj);
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
         let mut tmp_rec = HitRecord::new();
         let mut hit_anything: bool = false;
         let mut closest_so_far: f64 = t_max;
@@ -33,7 +47,10 @@ impl Hittable for HittableList {
                 hit_anything = true;
                 closest_so_far = tmp_rec.t;
                 rec.t = tmp_rec.t;
+                rec.u = tmp_rec.u;
+                rec.v = tmp_rec.v;
                 rec.norm = tmp_rec.norm;
+                rec.tangent = tmp_rec.tangent;
                 rec.p = tmp_rec.p;
                 rec.front = tmp_rec.front;
                 rec.material = tmp_rec.material;
@@ -42,7 +59,41 @@ impl Hittable for HittableList {
 
         hit_anything
     }
-}
 
-unsafe impl Send for HittableList {}
-unsafe impl Sync for HittableList {}
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        let mut tmp_box = Aabb::default();
+        let mut first_box = true;
+
+        for obj in self.objects.iter() {
+            if !obj.bounding_box(time0, time1, &mut tmp_box) {
+                return false;
+            }
+
+            *output_box = if first_box {
+                tmp_box
+            } else {
+                surrounding_box(*output_box, tmp_box)
+            };
+            first_box = false;
+        }
+
+        !first_box
+    }
+
+    fn pdf_value(&self, origin: Vec3, dir: Vec3, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.;
+        }
+
+        let weight = 1. / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|obj| weight * obj.pdf_value(origin, dir, time))
+            .sum()
+    }
+
+    fn random(&self, origin: Vec3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        let i = sampler.gen_range(0..self.objects.len());
+        self.objects[i].random(origin, time, sampler)
+    }
+}
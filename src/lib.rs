@@ -1,28 +1,49 @@
+pub mod aabb;
+pub mod adaptive;
+pub mod bvh;
 pub mod camera;
 pub mod color;
+pub mod film;
 pub mod hittable;
 pub mod hittable_list;
 pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod microfacet;
+pub mod noise;
+pub mod obj;
+pub mod onb;
+pub mod progressive;
+pub mod quad;
 pub mod ray;
+pub mod sampler;
+pub mod scene;
 pub mod sphere;
+pub mod texture;
+pub mod tonemap;
+pub mod transform;
+pub mod triangle;
 pub mod vec3;
 
 use crate::camera::Camera;
-use crate::color::{clamp, ray_color};
+use crate::color::{ray_color, Background};
+use crate::film::Film;
+use crate::hittable::Hittable;
 use crate::hittable_list::HittableList;
+use crate::medium::Media;
+use crate::sampler::Sampler;
+use crate::tonemap::ToneMapping;
 use crate::vec3::Vec3;
-use rand::Rng;
 use rayon::iter::{IndexedParallelIterator, ParallelIterator};
 use rayon::prelude::ParallelSliceMut;
-use std::ops::DerefMut;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, Ordering};
 
-use image::{Rgb, RgbImage};
-
-const PI: f64 = 3.1415926535897932385;
+const PI: f64 = std::f64::consts::PI;
 const INF: f64 = f64::INFINITY;
 
 fn deg_to_rad(deg: f64) -> f64 {
-    return deg * PI / 180.;
+    deg * PI / 180.
 }
 
 pub struct ImageConfig {
@@ -31,6 +52,13 @@ pub struct ImageConfig {
     pub height: u32,
     pub samples_per_pixel: u32,
     pub max_depth: u32,
+    pub background: Background,
+    /// Atmosphere and volumes tracked along every path.
+    pub media: Media,
+    /// Used by the film when it is exported to 8-bit formats.
+    pub tone_mapping: ToneMapping,
+    /// Renders with the same seed are identical, whatever the thread count.
+    pub seed: u64,
 }
 
 impl Default for ImageConfig {
@@ -41,48 +69,179 @@ impl Default for ImageConfig {
             height: (400. / 16. * 9.) as u32,
             samples_per_pixel: 100,
             max_depth: 50,
+            background: Background::default(),
+            media: Media::default(),
+            tone_mapping: ToneMapping::default(),
+            seed: 0,
         }
     }
 }
 
-pub fn render(world: HittableList, camera: Camera, config: ImageConfig) -> RgbImage {
-    let mut img = RgbImage::new(config.width, config.height);
+/// Traces the scene into a linear film, see `Film` for exporting it.
+pub fn render(world: impl Hittable, camera: Camera, config: ImageConfig) -> Film {
+    render_with_lights(world, HittableList::new(), camera, config)
+}
 
-    let stride = config.width as usize * 3;
+/// Like `render`, but also samples the emitters in `lights` directly at every
+/// bounce. The lights must be part of `world` as well.
+pub fn render_with_lights(
+    world: impl Hittable,
+    lights: HittableList,
+    camera: Camera,
+    config: ImageConfig,
+) -> Film {
+    render_with_progress(world, lights, camera, config, |_, _| {})
+}
 
-    img.deref_mut()
-        .par_chunks_exact_mut(stride)
+/// Like `render_with_lights`, calling `progress(rows_done, rows)` whenever a
+/// row of pixels is finished. Rows finish out of order and the callback runs
+/// on the worker threads.
+pub fn render_with_progress(
+    world: impl Hittable,
+    lights: HittableList,
+    camera: Camera,
+    config: ImageConfig,
+    progress: impl Fn(u32, u32) + Sync,
+) -> Film {
+    let rows_done = AtomicU32::new(0);
+
+    let mut film = Film::new(config.width, config.height);
+    film.tone_mapping = config.tone_mapping;
+
+    film.pixels_mut()
+        .par_chunks_exact_mut(config.width as usize)
         .enumerate()
         .for_each(|(y, row)| {
-            for (x, pixel) in row.chunks_exact_mut(3).enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
                 if x >= config.width as usize || y >= config.height as usize {
                     continue;
                 }
 
-                let mut color = Vec3::new(0., 0., 0.);
-
-                for _s in 0..config.samples_per_pixel {
-                    let u: f64 =
-                        (x as f64 + rand::thread_rng().gen::<f64>()) / (config.width as f64 - 1.);
-                    let v: f64 = ((config.height - y as u32) as f64
-                        + rand::thread_rng().gen::<f64>())
-                        / (config.height as f64 - 1.);
+                let color = sample_pixel(
+                    x as u32,
+                    y as u32,
+                    0..config.samples_per_pixel,
+                    &world,
+                    &lights,
+                    &camera,
+                    &config,
+                );
+
+                *pixel = color / config.samples_per_pixel as f64;
+            }
 
-                    let r = camera.get_ray(u, v);
-                    color += ray_color(&r, &world, config.max_depth);
-                }
+            progress(rows_done.fetch_add(1, Ordering::Relaxed) + 1, config.height);
+        });
 
-                let scale = 1. / config.samples_per_pixel as f64;
+    film
+}
 
-                let a = clamp((color.x * scale).sqrt(), 0., 0.999);
-                let b = clamp((color.y * scale).sqrt(), 0., 0.999);
-                let c = clamp((color.z * scale).sqrt(), 0., 0.999);
+// Sum of the radiance estimates through pixel `(x, y)`, counted from the top
+// row, for every sample index in `samples`. Each sample draws from its own
+// stream, so the result doesn't depend on which thread computes it.
+fn sample_pixel(
+    x: u32,
+    y: u32,
+    samples: Range<u32>,
+    world: &impl Hittable,
+    lights: &HittableList,
+    camera: &Camera,
+    config: &ImageConfig,
+) -> Vec3 {
+    let mut color = Vec3::new(0., 0., 0.);
+
+    for s in samples {
+        let mut sampler = Sampler::for_pixel(config.seed, x, y, s);
+
+        let u: f64 = (x as f64 + sampler.next_f64()) / (config.width as f64 - 1.);
+        let v: f64 =
+            ((config.height - y) as f64 + sampler.next_f64()) / (config.height as f64 - 1.);
+
+        let r = camera.get_ray(u, v, &mut sampler);
+        color += ray_color(
+            &r,
+            world,
+            lights,
+            &config.background,
+            &config.media,
+            config.max_depth,
+            &mut sampler,
+        );
+    }
 
-                let Rgb(a) = Rgb([(256. * a) as u8, (256. * b) as u8, (256. * c) as u8]);
+    color
+}
 
-                pixel.copy_from_slice(&a);
-            }
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Dielectric, DiffuseLight, Lambertian, MatType, Metal};
+    use crate::sphere::Sphere;
+    use std::sync::Arc;
+
+    fn render_on_threads(threads: usize) -> Vec<[u64; 3]> {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Vec3::new(0., -100.5, -1.),
+            100.,
+            MatType::Lambertian(Lambertian::new(Vec3::new(0.8, 0.8, 0.))),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Vec3::new(-1., 0., -1.),
+            0.5,
+            MatType::Dielectric(Dielectric {
+                index_refraction: 1.5,
+            }),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Vec3::new(1., 0., -1.),
+            0.5,
+            MatType::Metal(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.3)),
+        )));
+
+        let light = Arc::new(Sphere::new(
+            Vec3::new(0., 2., -1.),
+            0.5,
+            MatType::DiffuseLight(DiffuseLight::new(Vec3::new(4., 4., 4.))),
+        ));
+        world.add(light.clone());
+        let mut lights = HittableList::new();
+        lights.add(light);
+
+        let config = ImageConfig {
+            width: 24,
+            height: 16,
+            samples_per_pixel: 8,
+            max_depth: 8,
+            seed: 7,
+            ..ImageConfig::default()
+        };
+        let camera = Camera::new(
+            Vec3::new(0., 0.5, 2.),
+            Vec3::new(0., 0., -1.),
+            Vec3::new(0., 1., 0.),
+            40.,
+            config.width as f64 / config.height as f64,
+            0.1,
+            3.,
+        );
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let film = pool.install(|| render_with_lights(world, lights, camera, config));
+
+        film.pixels()
+            .iter()
+            .map(|p| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+            .collect()
+    }
 
-    img
+    #[test]
+    fn same_seed_gives_same_film_for_any_thread_count() {
+        let single = render_on_threads(1);
+        assert_eq!(single, render_on_threads(4));
+        assert_eq!(single, render_on_threads(7));
+    }
 }
@@ -0,0 +1,327 @@
+use image::ImageFormat;
+use rs_tracer::adaptive::AdaptiveSampler;
+use rs_tracer::bvh::BvhNode;
+use rs_tracer::film::Film;
+use rs_tracer::hittable_list::HittableList;
+use rs_tracer::render_with_progress;
+use rs_tracer::scene::SceneDescription;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+Usage: rs-tracer <SCENE> [OPTIONS]
+
+Renders a TOML or JSON scene file.
+
+Options:
+  -W, --width <N>      image width in pixels
+  -H, --height <N>     image height in pixels, derived from the scene's
+                       aspect ratio when only the width is given
+  -s, --spp <N>        samples per pixel
+  -d, --depth <N>      maximum number of bounces per path
+  -t, --threads <N>    number of render threads, all cores by default
+      --seed <N>       seed for the sampler, the same seed gives the same
+                       image for any thread count
+  -o, --output <PATH>  output image, <SCENE name>.<FORMAT> by default
+  -f, --format <EXT>   png, jpg, bmp, tga, exr, hdr or pfm, taken from the
+                       output extension by default
+  -a, --adaptive <E>   sample each pixel until its relative error is below
+                       E, with --spp as the cap
+      --heatmap <PATH> write the per-pixel sample counts of an adaptive
+                       render as an image
+  -q, --quiet          do not print progress
+  -h, --help           print this message";
+
+/// Command line options, `None` where the scene file decides.
+#[derive(Default)]
+struct Args {
+    scene: PathBuf,
+    width: Option<u32>,
+    height: Option<u32>,
+    samples_per_pixel: Option<u32>,
+    max_depth: Option<u32>,
+    threads: Option<usize>,
+    seed: Option<u64>,
+    adaptive: Option<f64>,
+    heatmap: Option<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<String>,
+    quiet: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut scene = None;
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`.
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(format!("missing value for '{}'", flag))
+            };
+
+            match flag.as_str() {
+                "-W" | "--width" => parsed.width = Some(number(&flag, &value()?)?),
+                "-H" | "--height" => parsed.height = Some(number(&flag, &value()?)?),
+                "-s" | "--spp" => parsed.samples_per_pixel = Some(number(&flag, &value()?)?),
+                "-d" | "--depth" => parsed.max_depth = Some(number(&flag, &value()?)?),
+                "-t" | "--threads" => parsed.threads = Some(number(&flag, &value()?)?),
+                "--seed" => parsed.seed = Some(number(&flag, &value()?)?),
+                "-a" | "--adaptive" => parsed.adaptive = Some(number(&flag, &value()?)?),
+                "--heatmap" => parsed.heatmap = Some(PathBuf::from(value()?)),
+                "-o" | "--output" => parsed.output = Some(PathBuf::from(value()?)),
+                "-f" | "--format" => parsed.format = Some(value()?.to_ascii_lowercase()),
+                "-q" | "--quiet" => parsed.quiet = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option '{}'", arg));
+                }
+                _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+        }
+
+        parsed.scene = scene.ok_or("missing scene file")?;
+        if parsed.heatmap.is_some() && parsed.adaptive.is_none() {
+            return Err("--heatmap needs --adaptive".to_string());
+        }
+        Ok(parsed)
+    }
+
+    /// Applies the overrides to the scene, keeping its aspect ratio when only
+    /// one side of the image is given.
+    fn apply(&self, description: &mut SceneDescription) {
+        let image = &mut description.image;
+        let aspect_ratio = image.width as f64 / image.height as f64;
+
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => {
+                image.width = width;
+                image.height = height;
+            }
+            (Some(width), None) => {
+                image.width = width;
+                image.height = (width as f64 / aspect_ratio).round() as u32;
+            }
+            (None, Some(height)) => {
+                image.width = (height as f64 * aspect_ratio).round() as u32;
+                image.height = height;
+            }
+            (None, None) => {}
+        }
+
+        if let Some(spp) = self.samples_per_pixel {
+            image.samples_per_pixel = spp;
+        }
+        if let Some(depth) = self.max_depth {
+            image.max_depth = depth;
+        }
+        if let Some(seed) = self.seed {
+            image.seed = seed;
+        }
+    }
+
+    /// Output path and format, each filled in from the other if missing.
+    fn output(&self) -> (PathBuf, String) {
+        let extension = |path: &Path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_ascii_lowercase())
+        };
+
+        match (&self.output, &self.format) {
+            (Some(path), Some(format)) => (path.clone(), format.clone()),
+            (Some(path), None) => (
+                path.clone(),
+                extension(path).unwrap_or_else(|| "png".to_string()),
+            ),
+            (None, format) => {
+                let format = format.clone().unwrap_or_else(|| "png".to_string());
+                let stem = self.scene.file_stem().unwrap_or("render".as_ref());
+                (Path::new(stem).with_extension(&format), format)
+            }
+        }
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value, flag))
+}
+
+fn save(film: &Film, path: &Path, format: &str) -> image::ImageResult<()> {
+    match format {
+        "exr" => film.save_exr(path),
+        "hdr" => film.save_hdr(path),
+        "pfm" => film.save_pfm(path),
+        _ => match ImageFormat::from_extension(format) {
+            Some(image_format) => film.to_rgb_image().save_with_format(path, image_format),
+            None => film.to_rgb_image().save(path),
+        },
+    }
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+fn seconds(d: Duration) -> String {
+    format!("{:.2}s", d.as_secs_f64())
+}
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\nRun 'rs-tracer --help' for usage.", e);
+            process::exit(2);
+        }
+    };
+
+    let (output, format) = args.output();
+    let known = ["png", "jpg", "jpeg", "bmp", "tga", "exr", "hdr", "pfm"];
+    if !known.contains(&format.as_str()) {
+        fail(format!("unsupported output format '{}'", format));
+    }
+
+    if let Some(threads) = args.threads {
+        if let Err(e) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            fail(e);
+        }
+    }
+
+    let start = Instant::now();
+
+    let mut description = match SceneDescription::load(&args.scene) {
+        Ok(description) => description,
+        Err(e) => fail(e),
+    };
+    args.apply(&mut description);
+
+    let scene = match description.build() {
+        Ok(scene) => scene,
+        Err(e) => fail(format!("{}: {}", args.scene.display(), e)),
+    };
+
+    let camera = &description.camera;
+    let world = BvhNode::new(&scene.world, camera.shutter_open, camera.shutter_close);
+    let image = &description.image;
+
+    if !args.quiet {
+        eprintln!(
+            "Loaded {} ({} objects) in {}",
+            args.scene.display(),
+            description.objects.len(),
+            seconds(start.elapsed())
+        );
+        eprintln!(
+            "Rendering {}x{} at {} spp, depth {}, {} threads",
+            image.width,
+            image.height,
+            image.samples_per_pixel,
+            image.max_depth,
+            rayon::current_num_threads()
+        );
+    }
+
+    let render_start = Instant::now();
+    let pixels = image.width as f64 * image.height as f64;
+
+    let last_percent = AtomicU32::new(0);
+    let quiet = args.quiet;
+    let progress = |done: u32, rows: u32| {
+        let percent = done * 100 / rows;
+        if !quiet && last_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+            eprint!("\rRendering: {:3}% ({}/{} rows)", percent, done, rows);
+            io::stderr().flush().ok();
+        }
+    };
+
+    let (film, samples, heatmap) = match args.adaptive {
+        Some(relative_error) => {
+            if !args.quiet {
+                eprintln!(
+                    "Sampling adaptively to a relative error of {}",
+                    relative_error
+                );
+            }
+
+            let result = AdaptiveSampler::new()
+                .relative_error(relative_error)
+                .render_with_progress(
+                    world,
+                    HittableList::new(),
+                    scene.camera,
+                    scene.config,
+                    progress,
+                );
+
+            let samples = result.average_samples() * pixels;
+            let heatmap = result.heatmap();
+            (result.film, samples, Some(heatmap))
+        }
+        None => {
+            let film = render_with_progress(
+                world,
+                HittableList::new(),
+                scene.camera,
+                scene.config,
+                progress,
+            );
+            (film, pixels * image.samples_per_pixel as f64, None)
+        }
+    };
+
+    if !args.quiet {
+        eprintln!();
+    }
+
+    let render_time = render_start.elapsed();
+    if !args.quiet {
+        eprintln!(
+            "Rendered in {} ({:.1} spp on average, {:.2} Msamples/s)",
+            seconds(render_time),
+            samples / pixels,
+            samples / render_time.as_secs_f64() / 1e6
+        );
+    }
+
+    if let Err(e) = save(&film, &output, &format) {
+        fail(format!("{}: {}", output.display(), e));
+    }
+
+    if let (Some(path), Some(heatmap)) = (&args.heatmap, heatmap) {
+        if let Err(e) = heatmap.save(path) {
+            fail(format!("{}: {}", path.display(), e));
+        }
+    }
+
+    if !args.quiet {
+        eprintln!(
+            "Wrote {} (total {})",
+            output.display(),
+            seconds(start.elapsed())
+        );
+    }
+}
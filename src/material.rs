@@ -1,33 +1,90 @@
+use crate::microfacet::{
+    fresnel_conductor, fresnel_dielectric, reflect_about, refract_through, TrowbridgeReitz,
+};
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
 use crate::vec3::{dot, reflect, refract, unit_vector, Vec3};
-use crate::{hittable::HitRecord, ray::Ray};
-use rand::Rng;
+use crate::{hittable::HitRecord, ray::Ray, PI};
+use std::sync::Arc;
 
-pub trait Material {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool;
+/// Outcome of sampling a material.
+pub struct ScatterRecord {
+    pub ray: Ray,
+    /// BSDF times cosine over `pdf`, the factor the path throughput is
+    /// multiplied by.
+    pub throughput: Vec3,
+    /// Solid angle density of `ray.dir`. For specular scattering, the
+    /// probability of having picked that discrete direction instead.
+    pub pdf: f64,
+    /// The direction came from a delta lobe, so `eval` and `pdf` cannot
+    /// reproduce it and light sampling is pointless.
+    pub is_specular: bool,
 }
 
-#[derive(Clone, Copy)]
+/// Directions passed to `eval` and `pdf` are unit vectors pointing away from
+/// the surface: `wi` towards where light arrives from, `wo` towards where it
+/// leaves to (the previous vertex of the path).
+pub trait Material: Send + Sync {
+    /// Samples an incoming direction for light leaving along `-r_in.dir`
+    /// with random numbers from `sampler`, or returns `None` if the path ends
+    /// here.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord>;
+
+    /// BSDF times the cosine term. Zero for delta lobes.
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3;
+
+    /// Solid angle density with which `scatter` picks `wi` given `wo`. Zero
+    /// for delta lobes.
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64;
+
+    /// Light given off at surface coordinates `(u, v)` and point `p`.
+    fn emitted(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+        Vec3::new(0., 0., 0.)
+    }
+}
+
+/// Materials as stored in scene objects. The built-in materials are
+/// dispatched statically, anything else implementing `Material` can be used
+/// through `Custom`.
+#[derive(Clone)]
 pub enum MatType {
     Lambertian(Lambertian),
     Metal(Metal),
     Dielectric(Dielectric),
+    DiffuseLight(DiffuseLight),
+    Isotropic(Isotropic),
+    HenyeyGreenstein(HenyeyGreenstein),
+    Conductor(Conductor),
+    RoughDielectric(RoughDielectric),
+    Principled(Principled),
+    Custom(Arc<dyn Material>),
 }
 
 impl MatType {
+    pub fn custom(material: impl Material + 'static) -> Self {
+        MatType::Custom(Arc::new(material))
+    }
+
     pub fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        self.as_trait().scatter(r_in, rec, attenuation, scattered)
+        sampler: &mut Sampler,
+    ) -> Option<ScatterRecord> {
+        self.as_trait().scatter(r_in, rec, sampler)
+    }
+
+    pub fn emitted(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.as_trait().emitted(u, v, p)
+    }
+
+    pub fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        self.as_trait().eval(rec, wi, wo)
+    }
+
+    pub fn pdf(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+        self.as_trait().pdf(rec, wi, wo)
     }
 
     fn as_trait(&self) -> &dyn Material {
@@ -35,68 +92,104 @@ impl MatType {
             MatType::Metal(m) => m,
             MatType::Lambertian(l) => l,
             MatType::Dielectric(d) => d,
+            MatType::DiffuseLight(l) => l,
+            MatType::Isotropic(i) => i,
+            MatType::HenyeyGreenstein(h) => h,
+            MatType::Conductor(c) => c,
+            MatType::RoughDielectric(d) => d,
+            MatType::Principled(p) => p,
+            MatType::Custom(c) => c.as_ref(),
         }
     }
 }
 
 impl Default for MatType {
     fn default() -> Self {
-        MatType::Lambertian(Lambertian {
-            albedo: Vec3::new(0.1, 0.2, 0.5),
-        })
+        MatType::Lambertian(Lambertian::new(Vec3::new(0.1, 0.2, 0.5)))
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Lambertian {
-    pub albedo: Vec3,
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Vec3) -> Self {
+        Lambertian::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Lambertian { albedo }
+    }
 }
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_dir = rec.norm + Vec3::random_unit();
+    // Offsetting the normal by a random unit vector gives a cosine-weighted
+    // distribution, so the throughput is just the albedo.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let mut scatter_dir = rec.norm + Vec3::random_unit(sampler);
         if scatter_dir.near_zero() {
             scatter_dir = rec.norm;
         }
-        *scattered = Ray {
-            orig: rec.p,
-            dir: scatter_dir,
-            time: r_in.time,
-        };
-        *attenuation = self.albedo;
 
-        true
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir: scatter_dir,
+                time: r_in.time,
+            },
+            throughput: self.albedo.value(rec.u, rec.v, rec.p),
+            pdf: self.pdf(rec, unit_vector(scatter_dir), -unit_vector(r_in.dir)),
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, _wo: Vec3) -> Vec3 {
+        let cosine = dot(wi, rec.norm).max(0.);
+        self.albedo.value(rec.u, rec.v, rec.p) * (cosine / PI)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, _wo: Vec3) -> f64 {
+        dot(wi, rec.norm).max(0.) / PI
     }
 }
 
-#[derive(Clone, Copy)]
+/// Mirror blurred by `fuzz`, which is used as the alpha of an isotropic GGX
+/// microfacet distribution. A `fuzz` of zero reflects like a perfect mirror.
+#[derive(Clone)]
 pub struct Metal {
-    pub albedo: Vec3,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f64,
 }
 
+impl Metal {
+    pub fn new(albedo: Vec3, fuzz: f64) -> Self {
+        Metal::textured(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
+        Metal { albedo, fuzz }
+    }
+
+    fn distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::new(self.fuzz, self.fuzz)
+    }
+}
+
 impl Material for Metal {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        let reflected: Vec3 = reflect(unit_vector(r_in.dir), rec.norm);
-        *scattered = Ray {
-            orig: rec.p,
-            dir: reflected + self.fuzz * Vec3::random_unit(),
-            time: r_in.time,
-        };
-        *attenuation = self.albedo;
-        dot(scattered.dir, rec.norm) > 0.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let albedo = self.albedo.value(rec.u, rec.v, rec.p);
+        glossy_scatter(&self.distribution(), |_| albedo, r_in, rec, sampler)
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        let albedo = self.albedo.value(rec.u, rec.v, rec.p);
+        glossy_eval(&self.distribution(), |_| albedo, rec, wi, wo)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+        glossy_pdf(&self.distribution(), rec, wi, wo)
     }
 }
 
@@ -115,14 +208,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Vec3::new(1., 1., 1.);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
         let refraction_ratio = if rec.front {
             1. / self.index_refraction
         } else {
@@ -132,23 +218,917 @@ impl Material for Dielectric {
         let unit_dir = unit_vector(r_in.dir);
         let cos_theta = dot(-unit_dir, rec.norm).min(1.);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-        let dir;
 
-        if refraction_ratio * sin_theta > 1.
-            || Dielectric::reflectance(cos_theta, refraction_ratio)
-                > rand::thread_rng().gen::<f64>()
-        {
-            dir = reflect(unit_dir, rec.norm);
+        // Pick reflection or refraction in proportion to the Fresnel term,
+        // which keeps the throughput at one.
+        let reflect_prob = if refraction_ratio * sin_theta > 1. {
+            1.
         } else {
-            dir = refract(unit_dir, rec.norm, refraction_ratio);
+            Dielectric::reflectance(cos_theta, refraction_ratio)
+        };
+
+        let (dir, pdf) = if reflect_prob > sampler.next_f64() {
+            (reflect(unit_dir, rec.norm), reflect_prob)
+        } else {
+            (
+                refract(unit_dir, rec.norm, refraction_ratio),
+                1. - reflect_prob,
+            )
+        };
+
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir,
+                time: r_in.time,
+            },
+            throughput: Vec3::new(1., 1., 1.),
+            pdf,
+            is_specular: true,
+        })
+    }
+
+    fn eval(&self, _rec: &HitRecord, _wi: Vec3, _wo: Vec3) -> Vec3 {
+        Vec3::new(0., 0., 0.)
+    }
+
+    fn pdf(&self, _rec: &HitRecord, _wi: Vec3, _wo: Vec3) -> f64 {
+        0.
+    }
+}
+
+#[derive(Clone)]
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vec3) -> Self {
+        DiffuseLight::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn textured(emit: Arc<dyn Texture>) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _sampler: &mut Sampler,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn eval(&self, _rec: &HitRecord, _wi: Vec3, _wo: Vec3) -> Vec3 {
+        Vec3::new(0., 0., 0.)
+    }
+
+    fn pdf(&self, _rec: &HitRecord, _wi: Vec3, _wo: Vec3) -> f64 {
+        0.
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.emit.value(u, v, p)
+    }
+}
+
+/// Phase function of a medium that scatters equally in all directions.
+#[derive(Clone)]
+pub struct Isotropic {
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Vec3) -> Self {
+        Isotropic::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir: Vec3::random_unit(sampler),
+                time: r_in.time,
+            },
+            throughput: self.albedo.value(rec.u, rec.v, rec.p),
+            pdf: 1. / (4. * PI),
+            is_specular: false,
+        })
+    }
+
+    // No cosine term, the phase function is all there is.
+    fn eval(&self, rec: &HitRecord, _wi: Vec3, _wo: Vec3) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p) / (4. * PI)
+    }
+
+    fn pdf(&self, _rec: &HitRecord, _wi: Vec3, _wo: Vec3) -> f64 {
+        1. / (4. * PI)
+    }
+}
+
+/// Henyey-Greenstein phase function. Positive `g` favours scattering
+/// forwards, negative `g` backwards, and zero is isotropic.
+#[derive(Clone)]
+pub struct HenyeyGreenstein {
+    pub g: f64,
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64, albedo: Vec3) -> Self {
+        HenyeyGreenstein::textured(g, Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(g: f64, albedo: Arc<dyn Texture>) -> Self {
+        HenyeyGreenstein { g, albedo }
+    }
+
+    // Light going straight on has `wi` and `wo` opposite each other.
+    fn phase(&self, wi: Vec3, wo: Vec3) -> f64 {
+        let g = self.g;
+        let denom = 1. + g * g + 2. * g * dot(wi, wo);
+        (1. - g * g) / (4. * PI * denom * denom.max(1e-12).sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let g = self.g;
+
+        // Invert the CDF for the angle to the direction of travel.
+        let xi = sampler.next_f64();
+        let cos_theta = if g.abs() < 1e-3 {
+            1. - 2. * xi
+        } else {
+            let sq = (1. - g * g) / (1. - g + 2. * g * xi);
+            ((1. + g * g - sq * sq) / (2. * g)).clamp(-1., 1.)
+        };
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * sampler.next_f64();
+
+        let forward = unit_vector(r_in.dir);
+        let dir = Onb::build_from_w(forward).local(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir,
+                time: r_in.time,
+            },
+            throughput: self.albedo.value(rec.u, rec.v, rec.p),
+            pdf: self.phase(dir, -forward),
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p) * self.phase(wi, wo)
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+        self.phase(wi, wo)
+    }
+}
+
+/// Rough metal described by its complex index of refraction `eta + i k`,
+/// given per RGB channel, over a GGX microfacet distribution.
+#[derive(Clone, Copy)]
+pub struct Conductor {
+    pub eta: Vec3,
+    pub k: Vec3,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    pub fn new(eta: Vec3, k: Vec3, roughness: f64) -> Self {
+        Conductor::anisotropic(eta, k, roughness, roughness)
+    }
+
+    /// Roughness can differ along the surface, `roughness_u` applies along
+    /// its tangent (the direction of increasing texture `u`) and
+    /// `roughness_v` across it.
+    pub fn anisotropic(eta: Vec3, k: Vec3, roughness_u: f64, roughness_v: f64) -> Self {
+        Conductor {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::new(
+                TrowbridgeReitz::roughness_to_alpha(roughness_u),
+                TrowbridgeReitz::roughness_to_alpha(roughness_v),
+            ),
         }
+    }
 
-        *scattered = Ray {
+    pub fn gold(roughness: f64) -> Self {
+        Conductor::new(
+            Vec3::new(0.143, 0.375, 1.442),
+            Vec3::new(3.983, 2.386, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Conductor::new(
+            Vec3::new(0.200, 0.924, 1.102),
+            Vec3::new(3.913, 2.453, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Conductor::new(
+            Vec3::new(1.657, 0.880, 0.521),
+            Vec3::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Conductor::new(
+            Vec3::new(0.155, 0.117, 0.138),
+            Vec3::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    fn fresnel(&self, cos_i: f64) -> Vec3 {
+        fresnel_conductor(cos_i, self.eta, self.k)
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        glossy_scatter(&self.distribution, |c| self.fresnel(c), r_in, rec, sampler)
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        glossy_eval(&self.distribution, |c| self.fresnel(c), rec, wi, wo)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+        glossy_pdf(&self.distribution, rec, wi, wo)
+    }
+}
+
+// Reflection off a GGX microfacet surface, shared by `Conductor` and `Metal`.
+// `fresnel` gives the reflectance for the cosine between `wo` and the
+// microfacet normal. Surfaces that are effectively smooth reflect as a delta
+// lobe.
+fn glossy_scatter(
+    dist: &TrowbridgeReitz,
+    fresnel: impl Fn(f64) -> Vec3,
+    r_in: &Ray,
+    rec: &HitRecord,
+    sampler: &mut Sampler,
+) -> Option<ScatterRecord> {
+    let onb = Onb::build_from_w_and_tangent(rec.norm, rec.tangent);
+    let wo = onb.to_local(-unit_vector(r_in.dir));
+    if wo.z <= 0. {
+        return None;
+    }
+
+    if dist.effectively_smooth() {
+        return Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir: onb.local(Vec3::new(-wo.x, -wo.y, wo.z)),
+                time: r_in.time,
+            },
+            throughput: fresnel(wo.z),
+            pdf: 1.,
+            is_specular: true,
+        });
+    }
+
+    let wm = dist.sample_wm(wo, sampler.next_f64(), sampler.next_f64());
+    let wi = reflect_about(wo, wm);
+    if wi.z <= 0. {
+        return None;
+    }
+
+    // With visible normal sampling everything but Fresnel and the shadowing
+    // of `wi` cancels out.
+    Some(ScatterRecord {
+        ray: Ray {
             orig: rec.p,
-            dir,
+            dir: onb.local(wi),
             time: r_in.time,
+        },
+        throughput: fresnel(dot(wo, wm)) * (dist.g(wo, wi) / dist.g1(wo)),
+        pdf: dist.visible_d(wo, wm) / (4. * dot(wo, wm).abs()),
+        is_specular: false,
+    })
+}
+
+fn glossy_eval(
+    dist: &TrowbridgeReitz,
+    fresnel: impl Fn(f64) -> Vec3,
+    rec: &HitRecord,
+    wi: Vec3,
+    wo: Vec3,
+) -> Vec3 {
+    let onb = Onb::build_from_w_and_tangent(rec.norm, rec.tangent);
+    let (wi, wo) = (onb.to_local(wi), onb.to_local(wo));
+    if wi.z <= 0. || wo.z <= 0. || dist.effectively_smooth() {
+        return Vec3::new(0., 0., 0.);
+    }
+
+    let wm = wi + wo;
+    if wm.near_zero() {
+        return Vec3::new(0., 0., 0.);
+    }
+    let wm = unit_vector(wm);
+
+    fresnel(dot(wo, wm)) * (dist.d(wm) * dist.g(wo, wi) / (4. * wo.z))
+}
+
+fn glossy_pdf(dist: &TrowbridgeReitz, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+    let onb = Onb::build_from_w_and_tangent(rec.norm, rec.tangent);
+    let (wi, wo) = (onb.to_local(wi), onb.to_local(wo));
+    if wi.z <= 0. || wo.z <= 0. || dist.effectively_smooth() {
+        return 0.;
+    }
+
+    let wm = wi + wo;
+    if wm.near_zero() {
+        return 0.;
+    }
+    let wm = unit_vector(wm);
+
+    dist.visible_d(wo, wm) / (4. * dot(wo, wm).abs())
+}
+
+/// Frosted glass: a dielectric interface over a GGX microfacet distribution.
+#[derive(Clone, Copy)]
+pub struct RoughDielectric {
+    pub index_refraction: f64,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl RoughDielectric {
+    pub fn new(index_refraction: f64, roughness: f64) -> Self {
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness);
+        RoughDielectric {
+            index_refraction,
+            distribution: TrowbridgeReitz::new(alpha, alpha),
+        }
+    }
+
+    /// Ratio of the index behind the surface to the one in front of it.
+    fn eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front {
+            self.index_refraction
+        } else {
+            1. / self.index_refraction
+        }
+    }
+
+    /// Picks reflection or refraction off a visible microfacet in the local
+    /// shading frame.
+    fn sample_local(&self, wo: Vec3, eta: f64, sampler: &mut Sampler) -> Option<Vec3> {
+        let wm = self
+            .distribution
+            .sample_wm(wo, sampler.next_f64(), sampler.next_f64());
+        if sampler.next_f64() < fresnel_dielectric(dot(wo, wm), eta) {
+            Some(reflect_about(wo, wm))
+        } else {
+            refract_through(wo, wm, eta)
+        }
+    }
+
+    /// BSDF times cosine and sampling density in the local shading frame.
+    fn eval_local(&self, wi: Vec3, wo: Vec3, eta: f64) -> (f64, f64) {
+        let (cos_i, cos_o) = (wi.z, wo.z);
+        if cos_i == 0. || cos_o <= 0. {
+            return (0., 0.);
+        }
+
+        // Generalized half vector, which also covers transmission.
+        let reflect = cos_i > 0.;
+        let wm = if reflect { wi + wo } else { wi * eta + wo };
+        if wm.near_zero() {
+            return (0., 0.);
+        }
+        let wm = unit_vector(wm);
+        let wm = if wm.z < 0. { -wm } else { wm };
+
+        // Microfacets facing away from either direction contribute nothing.
+        if dot(wm, wi) * cos_i < 0. || dot(wm, wo) * cos_o < 0. {
+            return (0., 0.);
+        }
+
+        let dist = &self.distribution;
+        let fresnel = fresnel_dielectric(dot(wo, wm), eta);
+        let d_g = dist.d(wm) * dist.g(wo, wi);
+
+        if reflect {
+            let value = fresnel * d_g / (4. * cos_o);
+            let pdf = fresnel * dist.visible_d(wo, wm) / (4. * dot(wo, wm).abs());
+            (value, pdf)
+        } else {
+            let denom = (dot(wi, wm) + dot(wo, wm) / eta).powi(2);
+            // Radiance is compressed into a smaller solid angle when
+            // entering the denser medium.
+            let value = (1. - fresnel) * d_g * (dot(wi, wm) * dot(wo, wm) / (cos_o * denom)).abs()
+                / (eta * eta);
+            let pdf = (1. - fresnel) * dist.visible_d(wo, wm) * dot(wi, wm).abs() / denom;
+            (value, pdf)
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let onb = Onb::build_from_w(rec.norm);
+        let wo = onb.to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0. {
+            return None;
+        }
+
+        let eta = self.eta(rec);
+
+        if self.distribution.effectively_smooth() {
+            let n = Vec3::new(0., 0., 1.);
+            let fresnel = fresnel_dielectric(wo.z, eta);
+            let (wi, throughput, pdf) = if sampler.next_f64() < fresnel {
+                (reflect_about(wo, n), 1., fresnel)
+            } else {
+                (refract_through(wo, n, eta)?, 1. / (eta * eta), 1. - fresnel)
+            };
+
+            return Some(ScatterRecord {
+                ray: Ray {
+                    orig: rec.p,
+                    dir: onb.local(wi),
+                    time: r_in.time,
+                },
+                throughput: Vec3::new(throughput, throughput, throughput),
+                pdf,
+                is_specular: true,
+            });
+        }
+
+        let wi = self.sample_local(wo, eta, sampler)?;
+        let (value, pdf) = self.eval_local(wi, wo, eta);
+        if pdf == 0. {
+            return None;
+        }
+
+        let throughput = value / pdf;
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir: onb.local(wi),
+                time: r_in.time,
+            },
+            throughput: Vec3::new(throughput, throughput, throughput),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        if self.distribution.effectively_smooth() {
+            return Vec3::new(0., 0., 0.);
+        }
+
+        let onb = Onb::build_from_w(rec.norm);
+        let (value, _) = self.eval_local(onb.to_local(wi), onb.to_local(wo), self.eta(rec));
+        Vec3::new(value, value, value)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+        if self.distribution.effectively_smooth() {
+            return 0.;
+        }
+
+        let onb = Onb::build_from_w(rec.norm);
+        self.eval_local(onb.to_local(wi), onb.to_local(wo), self.eta(rec))
+            .1
+    }
+}
+
+/// Roughness of the clear coat lobe, which is always fairly glossy.
+const CLEARCOAT_ROUGHNESS: f64 = 0.2;
+
+/// Disney-style principled material. Every parameter is a texture so it can
+/// vary over the surface; scalar parameters are read from the first channel
+/// and are expected in `[0, 1]`.
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    /// Blends from a dielectric base to a metal tinted by `base_color`.
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    /// Dielectric reflectance at normal incidence, where 0.5 is 4%.
+    pub specular: Arc<dyn Texture>,
+    /// Tints the dielectric reflection towards the hue of `base_color`.
+    pub specular_tint: Arc<dyn Texture>,
+    /// Soft retro-reflective rim, mostly useful for cloth.
+    pub sheen: Arc<dyn Texture>,
+    /// Strength of a glossy, colourless coat on top.
+    pub clearcoat: Arc<dyn Texture>,
+    /// Blends the base from opaque to rough glass tinted by `base_color`.
+    pub transmission: Arc<dyn Texture>,
+    /// Stretches the specular highlights along the surface tangent, the
+    /// direction of increasing texture `u`.
+    pub anisotropic: Arc<dyn Texture>,
+    /// Index of refraction used by the transmission lobe.
+    pub ior: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Vec3) -> Self {
+        Principled::textured(Arc::new(SolidColor::new(base_color)))
+    }
+
+    pub fn textured(base_color: Arc<dyn Texture>) -> Self {
+        Principled {
+            base_color,
+            metallic: constant(0.),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            specular_tint: constant(0.),
+            sheen: constant(0.),
+            clearcoat: constant(0.),
+            transmission: constant(0.),
+            anisotropic: constant(0.),
+            ior: 1.5,
+        }
+    }
+
+    pub fn metallic(mut self, metallic: f64) -> Self {
+        self.metallic = constant(metallic);
+        self
+    }
+
+    pub fn roughness(mut self, roughness: f64) -> Self {
+        self.roughness = constant(roughness);
+        self
+    }
+
+    pub fn specular(mut self, specular: f64) -> Self {
+        self.specular = constant(specular);
+        self
+    }
+
+    pub fn specular_tint(mut self, specular_tint: f64) -> Self {
+        self.specular_tint = constant(specular_tint);
+        self
+    }
+
+    pub fn sheen(mut self, sheen: f64) -> Self {
+        self.sheen = constant(sheen);
+        self
+    }
+
+    pub fn clearcoat(mut self, clearcoat: f64) -> Self {
+        self.clearcoat = constant(clearcoat);
+        self
+    }
+
+    pub fn transmission(mut self, transmission: f64) -> Self {
+        self.transmission = constant(transmission);
+        self
+    }
+
+    pub fn anisotropic(mut self, anisotropic: f64) -> Self {
+        self.anisotropic = constant(anisotropic);
+        self
+    }
+
+    pub fn ior(mut self, ior: f64) -> Self {
+        self.ior = ior;
+        self
+    }
+
+    /// Looks up the textures at the hit point and derives the lobes.
+    fn lobes(&self, rec: &HitRecord) -> PrincipledLobes {
+        let value = |t: &Arc<dyn Texture>| t.value(rec.u, rec.v, rec.p).x.clamp(0., 1.);
+
+        let base = self.base_color.value(rec.u, rec.v, rec.p);
+        let metallic = value(&self.metallic);
+        let roughness = value(&self.roughness);
+        let transmission = (1. - metallic) * value(&self.transmission);
+
+        let white = Vec3::new(1., 1., 1.);
+        let lum = luminance(base);
+        let tint = if lum > 0. { base / lum } else { white };
+
+        let specular_tint = value(&self.specular_tint);
+        let dielectric_spec =
+            0.08 * value(&self.specular) * ((1. - specular_tint) * white + specular_tint * tint);
+
+        // Keep alpha away from zero so the lobes stay non-delta.
+        let aspect = (1. - 0.9 * value(&self.anisotropic)).sqrt();
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness);
+        let spec_dist =
+            TrowbridgeReitz::new((alpha / aspect).max(1e-3), (alpha * aspect).max(1e-3));
+
+        PrincipledLobes {
+            base,
+            roughness,
+            diffuse: (1. - metallic) * (1. - transmission),
+            sheen: value(&self.sheen) * (0.5 * white + 0.5 * tint),
+            specular: 1. - transmission,
+            spec0: (1. - metallic) * dielectric_spec + metallic * base,
+            spec_dist,
+            transmission,
+            glass: RoughDielectric {
+                index_refraction: self.ior,
+                distribution: spec_dist,
+            },
+            clearcoat: 0.25 * value(&self.clearcoat),
+        }
+    }
+
+    fn eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front {
+            self.ior
+        } else {
+            1. / self.ior
+        }
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let onb = Onb::build_from_w_and_tangent(rec.norm, rec.tangent);
+        let wo = onb.to_local(-unit_vector(r_in.dir));
+        if wo.z <= 0. {
+            return None;
+        }
+
+        let lobes = self.lobes(rec);
+        let eta = self.eta(rec);
+        let probs = lobes.probabilities(wo);
+
+        let pick = sampler.next_f64();
+        let mut lobe = 0;
+        let mut cdf = probs[0];
+        while lobe < 3 && pick >= cdf {
+            lobe += 1;
+            cdf += probs[lobe];
+        }
+
+        let wi = match lobe {
+            0 => unit_vector(Vec3::new(0., 0., 1.) + Vec3::random_unit(sampler)),
+            1 => reflect_about(
+                wo,
+                lobes
+                    .spec_dist
+                    .sample_wm(wo, sampler.next_f64(), sampler.next_f64()),
+            ),
+            2 => lobes.glass.sample_local(wo, eta, sampler)?,
+            _ => {
+                let wm =
+                    clearcoat_distribution().sample_wm(wo, sampler.next_f64(), sampler.next_f64());
+                reflect_about(wo, wm)
+            }
         };
 
-        true
+        let pdf = lobes.pdf(wi, wo, eta, &probs);
+        if pdf <= 0. || !pdf.is_finite() {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            ray: Ray {
+                orig: rec.p,
+                dir: onb.local(wi),
+                time: r_in.time,
+            },
+            throughput: lobes.eval(wi, wo, eta) / pdf,
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> Vec3 {
+        let onb = Onb::build_from_w_and_tangent(rec.norm, rec.tangent);
+        let (wi, wo) = (onb.to_local(wi), onb.to_local(wo));
+        if wo.z <= 0. {
+            return Vec3::new(0., 0., 0.);
+        }
+
+        self.lobes(rec).eval(wi, wo, self.eta(rec))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wi: Vec3, wo: Vec3) -> f64 {
+        let onb = Onb::build_from_w_and_tangent(rec.norm, rec.tangent);
+        let (wi, wo) = (onb.to_local(wi), onb.to_local(wo));
+        if wo.z <= 0. {
+            return 0.;
+        }
+
+        let lobes = self.lobes(rec);
+        let probs = lobes.probabilities(wo);
+        lobes.pdf(wi, wo, self.eta(rec), &probs)
+    }
+}
+
+/// A `Principled` material evaluated at one point. The weights of the
+/// diffuse, specular, transmission and clear coat lobes scale their
+/// contribution to the BSDF.
+struct PrincipledLobes {
+    base: Vec3,
+    roughness: f64,
+    diffuse: f64,
+    sheen: Vec3,
+    specular: f64,
+    spec0: Vec3,
+    spec_dist: TrowbridgeReitz,
+    transmission: f64,
+    glass: RoughDielectric,
+    clearcoat: f64,
+}
+
+impl PrincipledLobes {
+    /// Probabilities of sampling each lobe, roughly following how much
+    /// light it reflects towards `wo`.
+    fn probabilities(&self, wo: Vec3) -> [f64; 4] {
+        let weights = [
+            self.diffuse * luminance(self.base + self.sheen),
+            self.specular * luminance(schlick(self.spec0, wo.z)),
+            self.transmission,
+            self.clearcoat * schlick_weight(wo.z).max(0.04),
+        ];
+
+        let total: f64 = weights.iter().sum();
+        if total <= 0. {
+            return [0., 1., 0., 0.];
+        }
+
+        weights.map(|w| w / total)
+    }
+
+    fn eval(&self, wi: Vec3, wo: Vec3, eta: f64) -> Vec3 {
+        let mut f = Vec3::new(0., 0., 0.);
+
+        if wi.z > 0. && wo.z > 0. {
+            let wh = wi + wo;
+            if wh.near_zero() {
+                return f;
+            }
+            let wh = unit_vector(wh);
+            let cos_d = dot(wi, wh);
+
+            if self.diffuse > 0. {
+                // Burley diffuse with its grazing retro-reflection, plus sheen.
+                let fd90 = 0.5 + 2. * self.roughness * cos_d * cos_d;
+                let fi = 1. + (fd90 - 1.) * schlick_weight(wi.z);
+                let fo = 1. + (fd90 - 1.) * schlick_weight(wo.z);
+                let diffuse = self.base * (fi * fo / PI) + self.sheen * schlick_weight(cos_d);
+                f += self.diffuse * wi.z * diffuse;
+            }
+
+            if self.specular > 0. {
+                let dist = &self.spec_dist;
+                let d_g = dist.d(wh) * dist.g(wo, wi);
+                f += (self.specular * d_g / (4. * wo.z)) * schlick(self.spec0, cos_d);
+            }
+
+            if self.clearcoat > 0. {
+                let dist = clearcoat_distribution();
+                let fresnel = 0.04 + 0.96 * schlick_weight(cos_d);
+                let value = self.clearcoat * fresnel * dist.d(wh) * dist.g(wo, wi) / (4. * wo.z);
+                f += Vec3::new(value, value, value);
+            }
+        }
+
+        if self.transmission > 0. {
+            let (value, _) = self.glass.eval_local(wi, wo, eta);
+            let tint = if wi.z < 0. {
+                self.base
+            } else {
+                Vec3::new(1., 1., 1.)
+            };
+            f += (self.transmission * value) * tint;
+        }
+
+        f
+    }
+
+    fn pdf(&self, wi: Vec3, wo: Vec3, eta: f64, probs: &[f64; 4]) -> f64 {
+        let mut pdf = 0.;
+
+        if wi.z > 0. && wo.z > 0. {
+            let wh = wi + wo;
+            if wh.near_zero() {
+                return 0.;
+            }
+            let wh = unit_vector(wh);
+            let jacobian = 4. * dot(wo, wh).abs();
+
+            pdf += probs[0] * wi.z / PI;
+            pdf += probs[1] * self.spec_dist.visible_d(wo, wh) / jacobian;
+            pdf += probs[3] * clearcoat_distribution().visible_d(wo, wh) / jacobian;
+        }
+
+        if probs[2] > 0. {
+            pdf += probs[2] * self.glass.eval_local(wi, wo, eta).1;
+        }
+
+        pdf
+    }
+}
+
+fn clearcoat_distribution() -> TrowbridgeReitz {
+    let alpha = TrowbridgeReitz::roughness_to_alpha(CLEARCOAT_ROUGHNESS);
+    TrowbridgeReitz::new(alpha, alpha)
+}
+
+fn constant(value: f64) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new(Vec3::new(value, value, value)))
+}
+
+pub(crate) fn luminance(c: Vec3) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+fn schlick_weight(cosine: f64) -> f64 {
+    (1. - cosine).clamp(0., 1.).powi(5)
+}
+
+/// Schlick's approximation of Fresnel reflectance with normal incidence
+/// reflectance `f0`.
+fn schlick(f0: Vec3, cosine: f64) -> Vec3 {
+    f0 + (Vec3::new(1., 1., 1.) - f0) * schlick_weight(cosine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit_record(material: &MatType) -> HitRecord<'_> {
+        let mut rec = HitRecord::new();
+        rec.norm = Vec3::new(0., 0., 1.);
+        rec.tangent = Vec3::new(1., 0., 0.);
+        rec.front = true;
+        rec.material = material;
+        rec
+    }
+
+    fn incoming() -> Ray {
+        Ray {
+            orig: Vec3::new(-1., 0.3, 1.),
+            dir: Vec3::new(1., -0.3, -1.),
+            time: 0.,
+        }
+    }
+
+    #[test]
+    fn fuzzed_metal_samples_its_own_density() {
+        let material = MatType::Metal(Metal::new(Vec3::new(0.9, 0.6, 0.3), 0.3));
+        let rec = hit_record(&material);
+        let r_in = incoming();
+        let wo = -unit_vector(r_in.dir);
+        let mut sampler = Sampler::new(1);
+
+        let mut sampled = 0;
+        for _ in 0..64 {
+            let Some(srec) = material.scatter(&r_in, &rec, &mut sampler) else {
+                continue;
+            };
+            let wi = unit_vector(srec.ray.dir);
+            assert!(!srec.is_specular);
+
+            let pdf = material.pdf(&rec, wi, wo);
+            assert!(pdf > 0.);
+            assert!((srec.pdf - pdf).abs() < 1e-9 * pdf);
+
+            let expected = material.eval(&rec, wi, wo) / pdf;
+            assert!((srec.throughput - expected).near_zero());
+            sampled += 1;
+        }
+        assert!(sampled > 0);
+    }
+
+    #[test]
+    fn unfuzzed_metal_is_a_mirror() {
+        let material = MatType::Metal(Metal::new(Vec3::new(0.9, 0.6, 0.3), 0.));
+        let rec = hit_record(&material);
+        let r_in = incoming();
+
+        let srec = material.scatter(&r_in, &rec, &mut Sampler::new(1)).unwrap();
+        assert!(srec.is_specular);
+        assert!((unit_vector(srec.ray.dir) - unit_vector(Vec3::new(1., -0.3, 1.))).near_zero());
+
+        let wo = -unit_vector(r_in.dir);
+        let wi = unit_vector(srec.ray.dir);
+        assert_eq!(material.pdf(&rec, wi, wo), 0.);
+        assert!(material.eval(&rec, wi, wo).near_zero());
     }
 }
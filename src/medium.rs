@@ -0,0 +1,682 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{Isotropic, MatType};
+use crate::noise::Perlin;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use crate::INF;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Volume of uniform density filling a boundary shape, such as fog or smoke.
+/// The boundary must be convex, since rays are only tracked between the
+/// first entry and exit point. It can go into the world like any other
+/// hittable, or into `Media`, where shadow rays see through it instead of
+/// being blocked.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    density: f64,
+    phase_function: MatType,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Vec3) -> Self {
+        ConstantMedium::with_phase(
+            boundary,
+            density,
+            MatType::Isotropic(Isotropic::new(albedo)),
+        )
+    }
+
+    pub fn textured(boundary: Arc<dyn Hittable>, density: f64, albedo: Arc<dyn Texture>) -> Self {
+        ConstantMedium::with_phase(
+            boundary,
+            density,
+            MatType::Isotropic(Isotropic::textured(albedo)),
+        )
+    }
+
+    /// Uses `phase_function` to scatter light inside the volume. It should be
+    /// a material that scatters in all directions, not just off a surface.
+    pub fn with_phase(boundary: Arc<dyn Hittable>, density: f64, phase_function: MatType) -> Self {
+        ConstantMedium {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+
+    // Part of `[t_min, t_max]` along `r` that lies inside the boundary.
+    fn inside(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut rec1 = HitRecord::new();
+        let mut rec2 = HitRecord::new();
+
+        if !self.boundary.hit(r, -INF, INF, &mut rec1) {
+            return None;
+        }
+        if !self.boundary.hit(r, rec1.t + 0.0001, INF, &mut rec2) {
+            return None;
+        }
+
+        let t_enter = rec1.t.max(t_min);
+        let t_exit = rec2.t.min(t_max);
+        (t_enter < t_exit).then_some((t_enter, t_exit))
+    }
+}
+
+impl Volume for ConstantMedium {
+    fn sample_collision(
+        &self,
+        r: &Ray,
+        t_max: f64,
+        sampler: &mut Sampler,
+    ) -> Option<MediumEvent<'_>> {
+        let (t_enter, t_exit) = self.inside(r, 0., t_max)?;
+
+        // Exponential free-flight distance to the next collision.
+        let ray_length = r.dir.len();
+        let distance = -(1. - sampler.next_f64()).ln() / self.density;
+        if distance > (t_exit - t_enter) * ray_length {
+            return None;
+        }
+
+        Some(MediumEvent::Scatter {
+            t: t_enter + distance / ray_length,
+            phase_function: &self.phase_function,
+        })
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64, _sampler: &mut Sampler) -> f64 {
+        match self.inside(r, t_min, t_max) {
+            Some((t_enter, t_exit)) => (-self.density * (t_exit - t_enter) * r.dir.len()).exp(),
+            None => 1.,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        let (t_enter, t_exit) = match self.inside(r, t_min.max(0.), t_max) {
+            Some(segment) => segment,
+            None => return false,
+        };
+
+        // Exponential free-flight distance to the next collision. `hit` gets
+        // no sampler, so the random number is keyed by the ray itself, which
+        // keeps renders reproducible.
+        let xi = Sampler::from_keys(&[
+            r.orig.x.to_bits(),
+            r.orig.y.to_bits(),
+            r.orig.z.to_bits(),
+            r.dir.x.to_bits(),
+            r.dir.y.to_bits(),
+            r.dir.z.to_bits(),
+            r.time.to_bits(),
+        ])
+        .next_f64();
+        let ray_length = r.dir.len();
+        let distance = -(1. - xi).ln() / self.density;
+        if distance > (t_exit - t_enter) * ray_length {
+            return false;
+        }
+
+        medium_hit(
+            r,
+            t_enter + distance / ray_length,
+            &self.phase_function,
+            rec,
+        );
+        true
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.boundary.bounding_box(time0, time1, output_box)
+    }
+}
+
+/// A volume tracked by `Media`. Every random decision comes from the
+/// sampler of the path.
+pub trait Volume: Send + Sync {
+    /// Samples the first real collision along `r` between 0 and `t_max`.
+    fn sample_collision(
+        &self,
+        r: &Ray,
+        t_max: f64,
+        sampler: &mut Sampler,
+    ) -> Option<MediumEvent<'_>>;
+
+    /// Fraction of light that makes it through the volume between `t_min`
+    /// and `t_max` along `r`.
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> f64;
+}
+
+/// Media that the path loop tracks itself instead of finding them through
+/// `hit`, so that shadow rays can estimate the light getting through them.
+#[derive(Clone, Default)]
+pub struct Media {
+    pub atmosphere: Option<Atmosphere>,
+    pub volumes: Vec<Arc<dyn Volume>>,
+}
+
+/// A real collision of a ray with a medium.
+pub enum MediumEvent<'a> {
+    /// Light is scattered at `t` according to the phase function.
+    Scatter { t: f64, phase_function: &'a MatType },
+    /// Light is absorbed at `t`, which ends the path after picking up the
+    /// radiance `emission` given off there.
+    Absorb { t: f64, emission: Vec3 },
+}
+
+impl MediumEvent<'_> {
+    pub fn t(&self) -> f64 {
+        match self {
+            MediumEvent::Scatter { t, .. } | MediumEvent::Absorb { t, .. } => *t,
+        }
+    }
+}
+
+impl Media {
+    pub fn is_empty(&self) -> bool {
+        self.atmosphere.is_none() && self.volumes.is_empty()
+    }
+
+    /// Samples the first real collision along `r` before `t_max`. The
+    /// atmosphere is skipped for rays that leave the scene, which is when
+    /// `t_max` is infinite.
+    pub fn sample_collision(
+        &self,
+        r: &Ray,
+        t_max: f64,
+        sampler: &mut Sampler,
+    ) -> Option<MediumEvent<'_>> {
+        let mut closest: Option<MediumEvent> = None;
+        let mut t_closest = t_max;
+
+        if let Some(atmosphere) = &self.atmosphere {
+            if t_max < INF {
+                if let Some(t) = atmosphere.sample_distance(r, t_closest, sampler) {
+                    t_closest = t;
+                    closest = Some(MediumEvent::Scatter {
+                        t,
+                        phase_function: &atmosphere.phase_function,
+                    });
+                }
+            }
+        }
+
+        // Extinction adds up where media overlap, so the first collision is
+        // the closest of the ones sampled in each medium on its own.
+        for volume in &self.volumes {
+            if let Some(event) = volume.sample_collision(r, t_closest, sampler) {
+                t_closest = event.t();
+                closest = Some(event);
+            }
+        }
+
+        closest
+    }
+
+    /// Fraction of light that makes it through all media between `t_min`
+    /// and `t_max` along `r`.
+    pub fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> f64 {
+        let mut transmittance = self
+            .atmosphere
+            .as_ref()
+            .map_or(1., |a| a.transmittance(r, t_min, t_max));
+
+        for volume in &self.volumes {
+            if transmittance == 0. {
+                break;
+            }
+            transmittance *= volume.transmittance(r, t_min, t_max, sampler);
+        }
+
+        transmittance
+    }
+}
+
+/// Homogeneous medium filling all the space between surfaces. Rays that
+/// leave the scene reach the background unattenuated, so the background
+/// stays visible behind the haze.
+#[derive(Clone)]
+pub struct Atmosphere {
+    pub density: f64,
+    pub phase_function: MatType,
+}
+
+impl Atmosphere {
+    pub fn new(density: f64, albedo: Vec3) -> Self {
+        Atmosphere {
+            density,
+            phase_function: MatType::Isotropic(Isotropic::new(albedo)),
+        }
+    }
+
+    /// Samples where along `r` light first collides with the medium, if that
+    /// happens before `t_max`.
+    pub fn sample_distance(&self, r: &Ray, t_max: f64, sampler: &mut Sampler) -> Option<f64> {
+        let distance = -(1. - sampler.next_f64()).ln() / self.density;
+        let t = distance / r.dir.len();
+
+        (t < t_max).then_some(t)
+    }
+
+    /// Fraction of light that makes it through a segment of `r`.
+    pub fn transmittance(&self, r: &Ray, t0: f64, t1: f64) -> f64 {
+        (-self.density * (t1 - t0) * r.dir.len()).exp()
+    }
+}
+
+/// Fills `rec` for a scattering event inside a medium. Media have no surface,
+/// so the normal is arbitrary and the phase function ignores it.
+pub(crate) fn medium_hit<'a>(
+    r: &Ray,
+    t: f64,
+    phase_function: &'a MatType,
+    rec: &mut HitRecord<'a>,
+) {
+    rec.t = t;
+    rec.p = r.at(t);
+    rec.norm = Vec3::new(1., 0., 0.);
+    rec.tangent = Vec3::new(0., 0., 0.);
+    rec.front = true;
+    rec.u = 0.;
+    rec.v = 0.;
+    rec.material = phase_function;
+}
+
+/// Spatially varying density of a medium.
+pub trait DensityField: Send + Sync {
+    fn density(&self, p: Vec3) -> f64;
+
+    /// Upper bound of `density` everywhere, used as the majorant for delta
+    /// tracking. A loose bound is correct but slower.
+    fn max_density(&self) -> f64;
+}
+
+/// Volume whose density varies through space, tracked with delta tracking
+/// for collisions and ratio tracking for transmittance. Coefficients are per
+/// unit density.
+pub struct HeterogeneousMedium {
+    pub bounds: Aabb,
+    pub field: Arc<dyn DensityField>,
+    pub absorption: f64,
+    pub scattering: f64,
+    /// Radiance given off by the absorbing part of the medium, as in fire.
+    pub emission: Vec3,
+    pub phase_function: MatType,
+}
+
+impl HeterogeneousMedium {
+    /// A white, purely scattering medium with an isotropic phase function.
+    pub fn new(bounds: Aabb, field: Arc<dyn DensityField>) -> Self {
+        HeterogeneousMedium {
+            bounds,
+            field,
+            absorption: 0.,
+            scattering: 1.,
+            emission: Vec3::new(0., 0., 0.),
+            phase_function: MatType::Isotropic(Isotropic::new(Vec3::new(1., 1., 1.))),
+        }
+    }
+
+    pub fn absorption(mut self, absorption: f64) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    pub fn scattering(mut self, scattering: f64) -> Self {
+        self.scattering = scattering;
+        self
+    }
+
+    pub fn emission(mut self, emission: Vec3) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    pub fn phase_function(mut self, phase_function: MatType) -> Self {
+        self.phase_function = phase_function;
+        self
+    }
+
+    fn majorant(&self) -> f64 {
+        (self.absorption + self.scattering) * self.field.max_density()
+    }
+}
+
+impl Volume for HeterogeneousMedium {
+    /// Delta tracking: tentative collisions are sampled against the
+    /// majorant, and each is real with probability extinction over majorant.
+    fn sample_collision(
+        &self,
+        r: &Ray,
+        t_max: f64,
+        sampler: &mut Sampler,
+    ) -> Option<MediumEvent<'_>> {
+        let (t0, t1) = self.bounds.hit_range(r, 0., t_max)?;
+        let majorant = self.majorant();
+        if majorant <= 0. {
+            return None;
+        }
+
+        let ray_length = r.dir.len();
+        let mut t = t0;
+
+        loop {
+            t -= (1. - sampler.next_f64()).ln() / (majorant * ray_length);
+            if t >= t1 {
+                return None;
+            }
+
+            let density = self.field.density(r.at(t));
+            let xi = sampler.next_f64() * majorant;
+
+            if xi < self.absorption * density {
+                return Some(MediumEvent::Absorb {
+                    t,
+                    emission: self.emission,
+                });
+            }
+
+            if xi < (self.absorption + self.scattering) * density {
+                return Some(MediumEvent::Scatter {
+                    t,
+                    phase_function: &self.phase_function,
+                });
+            }
+        }
+    }
+
+    /// Ratio tracking: the same tentative collisions, but each one scales the
+    /// estimate by the chance it was a null collision.
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64, sampler: &mut Sampler) -> f64 {
+        let (t0, t1) = match self.bounds.hit_range(r, t_min, t_max) {
+            Some(range) => range,
+            None => return 1.,
+        };
+        let majorant = self.majorant();
+        if majorant <= 0. {
+            return 1.;
+        }
+
+        let ray_length = r.dir.len();
+        let mut t = t0;
+        let mut transmittance = 1.;
+
+        loop {
+            t -= (1. - sampler.next_f64()).ln() / (majorant * ray_length);
+            if t >= t1 {
+                return transmittance;
+            }
+
+            let density = self.field.density(r.at(t));
+            transmittance *= 1. - (self.absorption + self.scattering) * density / majorant;
+            if transmittance <= 0. {
+                return 0.;
+            }
+        }
+    }
+}
+
+/// Densities stored at the centres of a regular grid of voxels spanning
+/// `bounds`, looked up with trilinear interpolation.
+pub struct VoxelGrid {
+    dims: [usize; 3],
+    data: Vec<f32>,
+    bounds: Aabb,
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    /// `data` holds `dims[0] * dims[1] * dims[2]` densities with x varying
+    /// fastest, then y, then z. `bounds` must have a positive, finite extent
+    /// along every axis.
+    pub fn new(dims: [usize; 3], data: Vec<f32>, bounds: Aabb) -> Self {
+        if dims.contains(&0) || Some(data.len()) != voxel_count(dims) {
+            panic!("Voxel data does not match the grid dimensions!");
+        }
+        let extent = bounds.max - bounds.min;
+        if ![extent.x, extent.y, extent.z]
+            .iter()
+            .all(|&e| e > 0. && e.is_finite())
+        {
+            panic!("Voxel grid bounds must have a positive, finite size!");
+        }
+
+        let max_density = data.iter().fold(0., |m: f64, &d| m.max(d as f64));
+
+        VoxelGrid {
+            dims,
+            data,
+            bounds,
+            max_density,
+        }
+    }
+
+    /// Fills the grid by sampling `density` at every voxel centre.
+    pub fn from_fn(dims: [usize; 3], bounds: Aabb, density: impl Fn(Vec3) -> f64) -> Self {
+        let size = bounds.max - bounds.min;
+        let mut data = Vec::with_capacity(dims[0] * dims[1] * dims[2]);
+
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    let p = bounds.min
+                        + Vec3::new(
+                            (x as f64 + 0.5) / dims[0] as f64 * size.x,
+                            (y as f64 + 0.5) / dims[1] as f64 * size.y,
+                            (z as f64 + 0.5) / dims[2] as f64 * size.z,
+                        );
+                    data.push(density(p) as f32);
+                }
+            }
+        }
+
+        VoxelGrid::new(dims, data, bounds)
+    }
+
+    /// Reads a grid from a raw volume file: an ASCII header line
+    /// `RSVOL <nx> <ny> <nz>` followed by the densities as little-endian
+    /// `f32`, in the order described in `new`.
+    pub fn load(path: impl AsRef<Path>, bounds: Aabb) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let header_end = bytes
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid("missing volume header"))?;
+        let header = std::str::from_utf8(&bytes[..header_end])
+            .map_err(|_| invalid("volume header is not text"))?;
+
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some("RSVOL") {
+            return Err(invalid("not a RSVOL volume file"));
+        }
+
+        let mut dims = [0; 3];
+        for dim in dims.iter_mut() {
+            *dim = fields
+                .next()
+                .and_then(|f| f.parse().ok())
+                .filter(|&d| d > 0)
+                .ok_or_else(|| invalid("invalid volume dimensions"))?;
+        }
+
+        // The header is untrusted, so its size must not overflow.
+        let size = voxel_count(dims)
+            .and_then(|n| n.checked_mul(4))
+            .ok_or_else(|| invalid("volume dimensions are too large"))?;
+
+        let body = &bytes[header_end + 1..];
+        if body.len() != size {
+            return Err(invalid("volume data does not match its dimensions"));
+        }
+
+        let data = body
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+
+        Ok(VoxelGrid::new(dims, data, bounds))
+    }
+
+    /// Writes the grid in the format read by `load`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let [nx, ny, nz] = self.dims;
+        let mut bytes = format!("RSVOL {} {} {}\n", nx, ny, nz).into_bytes();
+        for d in &self.data {
+            bytes.extend_from_slice(&d.to_le_bytes());
+        }
+
+        fs::write(path, bytes)
+    }
+
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.data[(z * self.dims[1] + y) * self.dims[0] + x] as f64
+    }
+}
+
+fn voxel_count(dims: [usize; 3]) -> Option<usize> {
+    dims[0].checked_mul(dims[1])?.checked_mul(dims[2])
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: Vec3) -> f64 {
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+        let mut frac = [0.; 3];
+
+        for a in 0..3 {
+            let axis = a as u32;
+            let (min, max, x) = (self.bounds.min[axis], self.bounds.max[axis], p[axis]);
+            if x < min || x > max {
+                return 0.;
+            }
+
+            // Continuous voxel coordinate, with voxel centres at integers.
+            let n = self.dims[a];
+            let g = ((x - min) / (max - min) * n as f64 - 0.5).clamp(0., (n - 1) as f64);
+            lo[a] = (g as usize).min(n - 1);
+            hi[a] = (lo[a] + 1).min(n - 1);
+            frac[a] = g - lo[a] as f64;
+        }
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let row =
+            |y: usize, z: usize| lerp(self.voxel(lo[0], y, z), self.voxel(hi[0], y, z), frac[0]);
+        let slice = |z: usize| lerp(row(lo[1], z), row(hi[1], z), frac[1]);
+
+        lerp(slice(lo[2]), slice(hi[2]), frac[2])
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
+
+/// Procedural density from fractal Perlin noise, between zero and `density`.
+pub struct NoiseDensity {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub octaves: u32,
+    pub density: f64,
+}
+
+impl NoiseDensity {
+    pub fn new(seed: u64, scale: f64, density: f64) -> Self {
+        NoiseDensity {
+            noise: Perlin::new(seed),
+            scale,
+            octaves: 4,
+            density,
+        }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: Vec3) -> f64 {
+        let n = self.noise.fbm(self.scale * p, self.octaves, 2., 0.5);
+        self.density * (0.5 * (1. + n)).clamp(0., 1.)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use crate::sphere::Sphere;
+    use crate::transform::{Instance, Transform};
+
+    fn fog(density: f64) -> Arc<ConstantMedium> {
+        let boundary = Arc::new(Sphere::new(Vec3::new(0., 0., 0.), 1., MatType::default()));
+        Arc::new(ConstantMedium::new(
+            boundary,
+            density,
+            Vec3::new(1., 1., 1.),
+        ))
+    }
+
+    fn ray(orig: Vec3) -> Ray {
+        Ray {
+            orig,
+            dir: Vec3::new(0., 0., -1.),
+            time: 0.,
+        }
+    }
+
+    #[test]
+    fn scatters_inside_boundary_when_hit_as_world_object() {
+        let mut world = HittableList::new();
+        world.add(fog(1e6));
+
+        let mut rec = HitRecord::new();
+        assert!(world.hit(&ray(Vec3::new(0., 0., 5.)), 0.001, INF, &mut rec));
+        assert!((rec.p.z - 1.).abs() < 1e-3);
+
+        assert!(!world.hit(&ray(Vec3::new(2., 0., 5.)), 0.001, INF, &mut rec));
+    }
+
+    #[test]
+    fn hit_is_reproducible_and_can_be_instanced() {
+        let r = ray(Vec3::new(0.1, 0.2, 5.));
+        let medium = fog(0.7);
+        let mut first = HitRecord::new();
+        let mut second = HitRecord::new();
+        let hit = medium.hit(&r, 0.001, INF, &mut first);
+        assert_eq!(hit, medium.hit(&r, 0.001, INF, &mut second));
+        assert_eq!(first.t, second.t);
+
+        let moved = Instance::new(fog(1e6), Transform::translate(Vec3::new(3., 0., 0.)));
+        let mut rec = HitRecord::new();
+        assert!(moved.hit(&ray(Vec3::new(3., 0., 5.)), 0.001, INF, &mut rec));
+        assert!((rec.p - Vec3::new(3., 0., 1.)).len() < 1e-3);
+    }
+
+    #[test]
+    fn transmittance_through_media_follows_beer_lambert() {
+        let mut media = Media::default();
+        media.volumes.push(fog(0.5));
+        let r = ray(Vec3::new(0., 0., 5.));
+
+        let t = media.transmittance(&r, 0., INF, &mut Sampler::new(0));
+        assert!((t - (-0.5f64 * 2.).exp()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::aabb::Aabb;
+use crate::bvh::{Bvh, BvhBuilder, BvhStats};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::MatType;
+use crate::ray::Ray;
+use crate::triangle::{intersect, triangle_bbox, TriangleHit};
+use crate::vec3::{cross, unit_vector, Vec3};
+
+/// Indexed triangle mesh sharing vertex data between faces, with its own
+/// BVH over the triangles. The geometry is read-only once built, since the
+/// BVH and the face order depend on it.
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    // Per-vertex normals, either empty or as long as `positions`.
+    normals: Vec<Vec3>,
+    // Per-vertex texture coordinates, either empty or as long as `positions`.
+    uvs: Vec<(f64, f64)>,
+    indices: Vec<[usize; 3]>,
+    pub material: MatType,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[usize; 3]>, material: MatType) -> Self {
+        TriangleMesh::with_builder(positions, indices, material, &BvhBuilder::default())
+    }
+
+    pub fn with_builder(
+        positions: Vec<Vec3>,
+        indices: Vec<[usize; 3]>,
+        material: MatType,
+        builder: &BvhBuilder,
+    ) -> Self {
+        for (i, tri) in indices.iter().enumerate() {
+            if tri.iter().any(|&v| v >= positions.len()) {
+                panic!("Triangle {} references a missing vertex!", i);
+            }
+        }
+
+        let boxes: Vec<Aabb> = indices
+            .iter()
+            .map(|&[a, b, c]| triangle_bbox(&[positions[a], positions[b], positions[c]]))
+            .collect();
+        let bvh = Bvh::build(&boxes, builder);
+
+        // Store faces in leaf order so traversal slots index them directly.
+        let indices = bvh.indices().iter().map(|&i| indices[i]).collect();
+
+        TriangleMesh {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices,
+            material,
+            bvh,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        if normals.len() != self.positions.len() {
+            panic!("Mesh needs exactly one normal per vertex!");
+        }
+
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        if uvs.len() != self.positions.len() {
+            panic!("Mesh needs exactly one uv per vertex!");
+        }
+
+        self.uvs = uvs;
+        self
+    }
+
+    /// Computes vertex normals by averaging the area-weighted normals of the
+    /// faces around each vertex.
+    pub fn with_smooth_normals(mut self) -> Self {
+        let mut normals = vec![Vec3::default(); self.positions.len()];
+
+        for &[a, b, c] in self.indices.iter() {
+            let n = cross(
+                self.positions[b] - self.positions[a],
+                self.positions[c] - self.positions[a],
+            );
+            normals[a] += n;
+            normals[b] += n;
+            normals[c] += n;
+        }
+
+        for n in normals.iter_mut() {
+            if !n.near_zero() {
+                *n = unit_vector(*n);
+            }
+        }
+
+        self.normals = normals;
+        self
+    }
+
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    /// Empty if the mesh has no vertex normals.
+    pub fn normals(&self) -> &[Vec3] {
+        &self.normals
+    }
+
+    /// Empty if the mesh has no texture coordinates.
+    pub fn uvs(&self) -> &[(f64, f64)] {
+        &self.uvs
+    }
+
+    /// Faces in BVH leaf order, which differs from the order they were
+    /// given in.
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.indices
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn bvh_stats(&self) -> BvhStats {
+        self.bvh.stats()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        let mut closest: Option<(usize, TriangleHit)> = None;
+
+        let hit_anything = self.bvh.traverse(r, t_min, t_max, |slot, closest_so_far| {
+            let [a, b, c] = self.indices[slot];
+            let (p0, p1, p2) = (self.positions[a], self.positions[b], self.positions[c]);
+
+            let (t, b1, b2) = intersect(r, p0, p1, p2, t_min, closest_so_far)?;
+            closest = Some((slot, TriangleHit { t, b1, b2 }));
+            Some(t)
+        });
+
+        // Only shade the closest face once traversal has finished.
+        if let Some((slot, hit)) = closest {
+            let [a, b, c] = self.indices[slot];
+            let vertices = [self.positions[a], self.positions[b], self.positions[c]];
+            let normals = if self.normals.is_empty() {
+                None
+            } else {
+                Some([self.normals[a], self.normals[b], self.normals[c]])
+            };
+            let uvs = if self.uvs.is_empty() {
+                None
+            } else {
+                Some([self.uvs[a], self.uvs[b], self.uvs[c]])
+            };
+
+            hit.fill(r, &vertices, normals, uvs, rec);
+            rec.material = &self.material;
+        }
+
+        hit_anything
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        match self.bvh.bbox() {
+            Some(bbox) => {
+                *output_box = bbox;
+                true
+            }
+            None => false,
+        }
+    }
+}
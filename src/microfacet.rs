@@ -0,0 +1,199 @@
+//! Trowbridge-Reitz (GGX) microfacet distribution and Fresnel terms. All
+//! directions are in a local shading frame with the normal along +z.
+
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+use crate::PI;
+
+/// Anisotropic Trowbridge-Reitz distribution with Smith masking-shadowing.
+#[derive(Debug, Clone, Copy)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        TrowbridgeReitz { alpha_x, alpha_y }
+    }
+
+    /// Maps perceptual roughness in `[0, 1]` to the distribution's alpha.
+    pub fn roughness_to_alpha(roughness: f64) -> f64 {
+        roughness * roughness
+    }
+
+    /// Below this roughness the surface is treated as perfectly smooth.
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// Density of microfacet normals `wm`.
+    pub fn d(&self, wm: Vec3) -> f64 {
+        let tan2 = tan2_theta(wm);
+        if tan2.is_infinite() {
+            return 0.;
+        }
+
+        let cos4 = cos2_theta(wm) * cos2_theta(wm);
+        let (cos_phi, sin_phi) = cos_sin_phi(wm);
+        let e = tan2 * ((cos_phi / self.alpha_x).powi(2) + (sin_phi / self.alpha_y).powi(2));
+
+        1. / (PI * self.alpha_x * self.alpha_y * cos4 * (1. + e) * (1. + e))
+    }
+
+    pub fn lambda(&self, w: Vec3) -> f64 {
+        let tan2 = tan2_theta(w);
+        if tan2.is_infinite() {
+            return 0.;
+        }
+
+        let (cos_phi, sin_phi) = cos_sin_phi(w);
+        let alpha2 = (cos_phi * self.alpha_x).powi(2) + (sin_phi * self.alpha_y).powi(2);
+
+        ((1. + alpha2 * tan2).sqrt() - 1.) / 2.
+    }
+
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1. / (1. + self.lambda(w))
+    }
+
+    /// Height-correlated masking-shadowing for the pair `wo`, `wi`.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1. / (1. + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of normals visible from `w`, which is also the density with
+    /// which `sample_wm` picks `wm`.
+    pub fn visible_d(&self, w: Vec3, wm: Vec3) -> f64 {
+        self.g1(w) / cos_theta(w).abs() * self.d(wm) * dot(w, wm).abs()
+    }
+
+    /// Samples a normal visible from `w` from two uniform numbers.
+    pub fn sample_wm(&self, w: Vec3, u1: f64, u2: f64) -> Vec3 {
+        // Stretch to the hemisphere configuration.
+        let mut wh = unit_vector(Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z));
+        if wh.z < 0. {
+            wh = -wh;
+        }
+
+        let t1 = if wh.z < 0.99999 {
+            unit_vector(cross(Vec3::new(0., 0., 1.), wh))
+        } else {
+            Vec3::new(1., 0., 0.)
+        };
+        let t2 = cross(wh, t1);
+
+        // Uniform disk point, warped towards the visible part of the
+        // projected hemisphere.
+        let r = u1.sqrt();
+        let phi = 2. * PI * u2;
+        let px = r * phi.cos();
+        let mut py = r * phi.sin();
+        let h = (1. - px * px).sqrt();
+        let t = (1. + wh.z) / 2.;
+        py = (1. - t) * h + t * py;
+        let pz = (1. - px * px - py * py).max(0.).sqrt();
+
+        let nh = px * t1 + py * t2 + pz * wh;
+
+        // Unstretch back to the ellipsoid configuration.
+        unit_vector(Vec3::new(
+            self.alpha_x * nh.x,
+            self.alpha_y * nh.y,
+            nh.z.max(1e-6),
+        ))
+    }
+}
+
+pub fn cos_theta(w: Vec3) -> f64 {
+    w.z
+}
+
+fn cos2_theta(w: Vec3) -> f64 {
+    w.z * w.z
+}
+
+fn tan2_theta(w: Vec3) -> f64 {
+    (1. - cos2_theta(w)).max(0.) / cos2_theta(w)
+}
+
+fn cos_sin_phi(w: Vec3) -> (f64, f64) {
+    let sin_theta = (1. - cos2_theta(w)).max(0.).sqrt();
+    if sin_theta == 0. {
+        return (1., 0.);
+    }
+
+    (
+        (w.x / sin_theta).clamp(-1., 1.),
+        (w.y / sin_theta).clamp(-1., 1.),
+    )
+}
+
+/// Mirror `wo` about `n`.
+pub fn reflect_about(wo: Vec3, n: Vec3) -> Vec3 {
+    -wo + 2. * dot(wo, n) * n
+}
+
+/// Refracts `wi` through a surface with normal `n` on its side, where `eta`
+/// is the ratio of the index on the far side to the one on `wi`'s side.
+/// Returns `None` on total internal reflection.
+pub fn refract_through(wi: Vec3, n: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = dot(n, wi);
+    let sin2_i = (1. - cos_i * cos_i).max(0.);
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1. {
+        return None;
+    }
+
+    let cos_t = (1. - sin2_t).sqrt();
+    Some(-wi / eta + (cos_i / eta - cos_t) * n)
+}
+
+/// Unpolarized Fresnel reflectance of a dielectric interface, `eta` being
+/// the relative index across it.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let mut cos_i = cos_i.clamp(-1., 1.);
+    let mut eta = eta;
+    if cos_i < 0. {
+        eta = 1. / eta;
+        cos_i = -cos_i;
+    }
+
+    let sin2_i = 1. - cos_i * cos_i;
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1. {
+        return 1.;
+    }
+
+    let cos_t = (1. - sin2_t).max(0.).sqrt();
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (r_parl * r_parl + r_perp * r_perp) / 2.
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index
+/// `eta + i k`, evaluated per channel.
+pub fn fresnel_conductor(cos_i: f64, eta: Vec3, k: Vec3) -> Vec3 {
+    let f = |eta: f64, k: f64| {
+        let cos_i = cos_i.clamp(0., 1.);
+        let cos2 = cos_i * cos_i;
+        let sin2 = 1. - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4. * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.).sqrt();
+        let t2 = 2. * cos_i * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        0.5 * (rp + rs)
+    };
+
+    Vec3::new(f(eta.x, k.x), f(eta.y, k.y), f(eta.z, k.z))
+}
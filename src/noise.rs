@@ -0,0 +1,231 @@
+use crate::sampler::{splitmix64, Sampler};
+use crate::vec3::{dot, unit_vector, Vec3};
+use rand::RngCore;
+
+const POINT_COUNT: usize = 256;
+
+/// Interpolation used between lattice points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Smoothing {
+    /// Plain trilinear blending, shows grid-aligned artifacts.
+    Trilinear,
+    /// Trilinear blending with Hermite-smoothed weights.
+    Hermite,
+}
+
+/// Gradient noise on an integer lattice. The same seed always produces the
+/// same noise, the lattice is drawn from the crate's own `Sampler` so it does
+/// not change with the `rand` version.
+pub struct Perlin {
+    ranvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+    pub smoothing: Smoothing,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Sampler::new(seed);
+
+        let ranvec = (0..POINT_COUNT)
+            .map(|_| {
+                let mut coord = || 2. * rng.next_f64() - 1.;
+                unit_vector(Vec3::new(coord(), coord(), coord()))
+            })
+            .collect();
+
+        // Fisher-Yates, the bias of the modulo is negligible for 256 entries.
+        let mut perm = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            for i in (1..POINT_COUNT).rev() {
+                p.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+            }
+            p
+        };
+        let perm_x = perm();
+        let perm_y = perm();
+        let perm_z = perm();
+
+        Perlin {
+            ranvec,
+            perm_x,
+            perm_y,
+            perm_z,
+            smoothing: Smoothing::Hermite,
+        }
+    }
+
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Noise value at `p`, roughly in `[-1, 1]`.
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let u = p.x - p.x.floor();
+        let v = p.y - p.y.floor();
+        let w = p.z - p.z.floor();
+
+        // Smoothing only changes the interpolation weights, the gradients
+        // still use the real offsets.
+        let (uu, vv, ww) = match self.smoothing {
+            Smoothing::Trilinear => (u, v, w),
+            Smoothing::Hermite => (
+                u * u * (3. - 2. * u),
+                v * v * (3. - 2. * v),
+                w * w * (3. - 2. * w),
+            ),
+        };
+
+        let i = p.x.floor() as i64;
+        let j = p.y.floor() as i64;
+        let k = p.z.floor() as i64;
+
+        let mut accum = 0.;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let hash = self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize];
+
+                    // Gradients are dotted with the offset from their own corner.
+                    let weight = Vec3::new(u - di as f64, v - dj as f64, w - dk as f64);
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+
+                    accum += (fi * uu + (1. - fi) * (1. - uu))
+                        * (fj * vv + (1. - fj) * (1. - vv))
+                        * (fk * ww + (1. - fk) * (1. - ww))
+                        * dot(self.ranvec[hash], weight);
+                }
+            }
+        }
+
+        accum
+    }
+
+    /// Absolute value of the sum of `depth` octaves of noise, each at double
+    /// the frequency and half the weight of the last.
+    pub fn turbulence(&self, p: Vec3, depth: u32) -> f64 {
+        let mut accum = 0.;
+        let mut temp_p = p;
+        let mut weight = 1.;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.;
+        }
+
+        accum.abs()
+    }
+
+    /// Fractional Brownian motion: `octaves` layers of signed noise, with
+    /// frequency scaled by `lacunarity` and amplitude by `gain` per layer.
+    pub fn fbm(&self, p: Vec3, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut accum = 0.;
+        let mut temp_p = p;
+        let mut amplitude = 1.;
+
+        for _ in 0..octaves {
+            accum += amplitude * self.noise(temp_p);
+            amplitude *= gain;
+            temp_p *= lacunarity;
+        }
+
+        accum
+    }
+}
+
+/// Which distances a Worley lookup reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorleyFeature {
+    /// Distance to the closest feature point.
+    F1,
+    /// Distance to the second closest feature point.
+    F2,
+    /// `F2 - F1`, bright along cell borders.
+    F2MinusF1,
+}
+
+/// Result of a Worley lookup.
+#[derive(Debug, Clone, Copy)]
+pub struct WorleySample {
+    pub f1: f64,
+    pub f2: f64,
+    /// Random value in `[0, 1)` shared by every point of the closest cell.
+    pub cell_id: f64,
+}
+
+impl WorleySample {
+    pub fn feature(&self, feature: WorleyFeature) -> f64 {
+        match feature {
+            WorleyFeature::F1 => self.f1,
+            WorleyFeature::F2 => self.f2,
+            WorleyFeature::F2MinusF1 => self.f2 - self.f1,
+        }
+    }
+}
+
+/// Cellular (Voronoi) noise with one jittered feature point per unit cell.
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Worley { seed }
+    }
+
+    pub fn sample(&self, p: Vec3) -> WorleySample {
+        let cell = [p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64];
+        let mut f1 = f64::INFINITY;
+        let mut f2 = f64::INFINITY;
+        let mut cell_id = 0.;
+
+        // With one point per cell the second closest point can lie two cells
+        // away, so search the surrounding 5x5x5 cells.
+        for di in -2..=2 {
+            for dj in -2..=2 {
+                for dk in -2..=2 {
+                    let c = [cell[0] + di, cell[1] + dj, cell[2] + dk];
+                    let h = self.hash(c);
+                    let feature = Vec3::new(
+                        c[0] as f64 + unit_float(h),
+                        c[1] as f64 + unit_float(splitmix64(h)),
+                        c[2] as f64 + unit_float(splitmix64(h ^ 0x9e37_79b9)),
+                    );
+
+                    let d = (feature - p).len();
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                        cell_id = unit_float(splitmix64(h ^ 0x632b_e5ab));
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+
+        WorleySample { f1, f2, cell_id }
+    }
+
+    pub fn noise(&self, p: Vec3, feature: WorleyFeature) -> f64 {
+        self.sample(p).feature(feature)
+    }
+
+    fn hash(&self, c: [i64; 3]) -> u64 {
+        let mut h = splitmix64(self.seed);
+        for v in c {
+            h = splitmix64(h ^ v as u64);
+        }
+        h
+    }
+}
+
+// Top 53 bits as a float in [0, 1).
+fn unit_float(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
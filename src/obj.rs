@@ -0,0 +1,490 @@
+use crate::hittable_list::HittableList;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, MatType, Metal};
+use crate::mesh::TriangleMesh;
+use crate::texture::ImageTexture;
+use crate::vec3::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum ObjErrorKind {
+    Io(std::io::Error),
+    /// A statement is missing one of its values.
+    MissingValue(&'static str),
+    InvalidNumber(String),
+    /// A face refers to a vertex, uv or normal that has not been declared.
+    IndexOutOfRange(String),
+    /// A face with fewer than three vertices.
+    DegenerateFace,
+    UnknownMaterial(String),
+    /// A texture map that could not be loaded.
+    Image(image::ImageError),
+}
+
+/// Error while loading an OBJ or MTL file. `line` is 1-based, and 0 when the
+/// file could not be read at all.
+#[derive(Debug)]
+pub struct ObjError {
+    pub file: PathBuf,
+    pub line: usize,
+    pub kind: ObjErrorKind,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "{}:{}: ", self.file.display(), self.line)?;
+        } else {
+            write!(f, "{}: ", self.file.display())?;
+        }
+
+        match &self.kind {
+            ObjErrorKind::Io(e) => write!(f, "{}", e),
+            ObjErrorKind::MissingValue(what) => write!(f, "missing {}", what),
+            ObjErrorKind::InvalidNumber(s) => write!(f, "invalid number '{}'", s),
+            ObjErrorKind::IndexOutOfRange(s) => write!(f, "index out of range in '{}'", s),
+            ObjErrorKind::DegenerateFace => write!(f, "face has fewer than 3 vertices"),
+            ObjErrorKind::UnknownMaterial(name) => write!(f, "unknown material '{}'", name),
+            ObjErrorKind::Image(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ObjErrorKind::Io(e) => Some(e),
+            ObjErrorKind::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Material as described by an MTL file.
+#[derive(Clone)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub kd: Vec3,
+    pub ks: Vec3,
+    pub ke: Vec3,
+    pub ns: f64,
+    pub ni: Option<f64>,
+    pub d: f64,
+    pub map_kd: Option<PathBuf>,
+    /// The image `map_kd` points to, loaded along with the library.
+    pub diffuse_texture: Option<Arc<ImageTexture>>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> Self {
+        ObjMaterial {
+            name: name.to_string(),
+            kd: Vec3::new(0.8, 0.8, 0.8),
+            ks: Vec3::default(),
+            ke: Vec3::default(),
+            ns: 0.,
+            ni: None,
+            d: 1.,
+            map_kd: None,
+            diffuse_texture: None,
+        }
+    }
+
+    /// Picks the closest built-in material: emitters become lights, anything
+    /// see-through glass, specular-dominated surfaces metal and the rest
+    /// diffuse, textured by the diffuse map if there is one.
+    pub fn to_mat_type(&self) -> MatType {
+        if !self.ke.near_zero() {
+            return MatType::DiffuseLight(DiffuseLight::new(self.ke));
+        }
+
+        if self.d < 1. {
+            return MatType::Dielectric(Dielectric {
+                index_refraction: self.ni.unwrap_or(1.5),
+            });
+        }
+
+        if max_component(self.ks) > max_component(self.kd) {
+            // Map the Phong exponent onto the equivalent Beckmann roughness.
+            let fuzz = (2. / (self.ns + 2.)).sqrt().min(1.);
+            return MatType::Metal(Metal::new(self.ks, fuzz));
+        }
+
+        match &self.diffuse_texture {
+            Some(texture) => MatType::Lambertian(Lambertian::textured(texture.clone())),
+            None => MatType::Lambertian(Lambertian::new(self.kd)),
+        }
+    }
+}
+
+fn max_component(v: Vec3) -> f64 {
+    v.x.max(v.y).max(v.z)
+}
+
+/// Reads every material from an MTL file, keyed by name.
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<HashMap<String, ObjMaterial>, ObjError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+    let mut materials = HashMap::new();
+    let mut current: Option<ObjMaterial> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let err = |kind| ObjError {
+            file: path.to_path_buf(),
+            line: i + 1,
+            kind,
+        };
+        let mut tokens = line.split_whitespace();
+
+        let keyword = match tokens.next() {
+            Some(k) if !k.starts_with('#') => k,
+            _ => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = tokens
+                .next()
+                .ok_or(err(ObjErrorKind::MissingValue("name")))?;
+            if let Some(done) = current.replace(ObjMaterial::new(name)) {
+                materials.insert(done.name.clone(), done);
+            }
+            continue;
+        }
+
+        // Statements before the first newmtl have nothing to apply to.
+        let mat = match current.as_mut() {
+            Some(mat) => mat,
+            None => continue,
+        };
+
+        match keyword {
+            "Kd" => mat.kd = parse_vec3(&mut tokens).map_err(err)?,
+            "Ks" => mat.ks = parse_vec3(&mut tokens).map_err(err)?,
+            "Ke" => mat.ke = parse_vec3(&mut tokens).map_err(err)?,
+            "Ns" => mat.ns = parse_f64(tokens.next(), "exponent").map_err(err)?,
+            "Ni" => mat.ni = Some(parse_f64(tokens.next(), "index").map_err(err)?),
+            "d" => mat.d = parse_f64(tokens.next(), "dissolve").map_err(err)?,
+            "Tr" => mat.d = 1. - parse_f64(tokens.next(), "transparency").map_err(err)?,
+            "map_Kd" => {
+                // Options may precede the file name, which always comes last.
+                let file = tokens
+                    .last()
+                    .ok_or(err(ObjErrorKind::MissingValue("file name")))?;
+                let file = sibling(path, file);
+                let texture = ImageTexture::load(&file).map_err(|e| err(ObjErrorKind::Image(e)))?;
+                mat.diffuse_texture = Some(Arc::new(texture));
+                mat.map_kd = Some(file);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(done) = current {
+        materials.insert(done.name.clone(), done);
+    }
+
+    Ok(materials)
+}
+
+// Triangles sharing a group and material, with OBJ's separate position, uv and
+// normal indices merged into the single index the mesh uses.
+struct MeshBuilder {
+    material: MatType,
+    vertex_map: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    positions: Vec<Vec3>,
+    uvs: Vec<Option<(f64, f64)>>,
+    normals: Vec<Option<Vec3>>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(material: MatType) -> Self {
+        MeshBuilder {
+            material,
+            vertex_map: HashMap::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn vertex(&mut self, key: (usize, Option<usize>, Option<usize>), obj: &ObjData) -> usize {
+        if let Some(&index) = self.vertex_map.get(&key) {
+            return index;
+        }
+
+        let (v, vt, vn) = key;
+        let index = self.positions.len();
+        self.positions.push(obj.positions[v]);
+        self.uvs.push(vt.map(|i| obj.uvs[i]));
+        self.normals.push(vn.map(|i| obj.normals[i]));
+        self.vertex_map.insert(key, index);
+        index
+    }
+
+    fn build(self) -> TriangleMesh {
+        let mut mesh = TriangleMesh::new(self.positions, self.indices, self.material);
+
+        // Attributes are only kept if every vertex of the mesh has them.
+        if let Some(uvs) = self.uvs.into_iter().collect::<Option<Vec<_>>>() {
+            mesh = mesh.with_uvs(uvs);
+        }
+        if let Some(normals) = self.normals.into_iter().collect::<Option<Vec<_>>>() {
+            mesh = mesh.with_normals(normals);
+        }
+
+        mesh
+    }
+}
+
+#[derive(Default)]
+struct ObjData {
+    positions: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+}
+
+/// Loads an OBJ file along with the MTL libraries it references, and adds
+/// one triangle mesh per group and material to `world`. Polygons are
+/// triangulated as fans, faces without a material use `MatType::default()`.
+pub fn load_obj(path: impl AsRef<Path>, world: &mut HittableList) -> Result<(), ObjError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+
+    let mut data = ObjData::default();
+    let mut materials: HashMap<String, ObjMaterial> = HashMap::new();
+    let mut meshes: Vec<MeshBuilder> = Vec::new();
+    let mut mesh_lookup: HashMap<(String, Option<String>), usize> = HashMap::new();
+    let mut group = String::new();
+    let mut material: Option<String> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let err = |kind| ObjError {
+            file: path.to_path_buf(),
+            line: i + 1,
+            kind,
+        };
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => data.positions.push(parse_vec3(&mut tokens).map_err(err)?),
+            Some("vt") => {
+                let u = parse_f64(tokens.next(), "u").map_err(err)?;
+                let v = match tokens.next() {
+                    Some(v) => parse_f64(Some(v), "v").map_err(err)?,
+                    None => 0.,
+                };
+                data.uvs.push((u, v));
+            }
+            Some("vn") => data.normals.push(parse_vec3(&mut tokens).map_err(err)?),
+            Some("g") | Some("o") => group = tokens.collect::<Vec<_>>().join(" "),
+            Some("mtllib") => {
+                for file in tokens {
+                    materials.extend(load_mtl(sibling(path, file))?);
+                }
+            }
+            Some("usemtl") => {
+                let name = tokens
+                    .next()
+                    .ok_or(err(ObjErrorKind::MissingValue("name")))?;
+                if !materials.contains_key(name) {
+                    return Err(err(ObjErrorKind::UnknownMaterial(name.to_string())));
+                }
+                material = Some(name.to_string());
+            }
+            Some("f") => {
+                let keys = tokens
+                    .map(|vertex| parse_face_vertex(vertex, &data))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(err)?;
+                if keys.len() < 3 {
+                    return Err(err(ObjErrorKind::DegenerateFace));
+                }
+
+                let lookup = (group.clone(), material.clone());
+                let slot = *mesh_lookup.entry(lookup).or_insert_with(|| {
+                    let mat = material
+                        .as_ref()
+                        .map_or(MatType::default(), |name| materials[name].to_mat_type());
+                    meshes.push(MeshBuilder::new(mat));
+                    meshes.len() - 1
+                });
+                let mesh = &mut meshes[slot];
+
+                let indices: Vec<usize> = keys.into_iter().map(|k| mesh.vertex(k, &data)).collect();
+                for k in 1..indices.len() - 1 {
+                    mesh.indices.push([indices[0], indices[k], indices[k + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for mesh in meshes {
+        world.add(Arc::new(mesh.build()));
+    }
+
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|e| ObjError {
+        file: path.to_path_buf(),
+        line: 0,
+        kind: ObjErrorKind::Io(e),
+    })
+}
+
+// Resolves a file named inside `path` relative to the directory containing it.
+fn sibling(path: &Path, file: &str) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join(file)
+}
+
+fn parse_f64(token: Option<&str>, what: &'static str) -> Result<f64, ObjErrorKind> {
+    let token = token.ok_or(ObjErrorKind::MissingValue(what))?;
+    token
+        .parse()
+        .map_err(|_| ObjErrorKind::InvalidNumber(token.to_string()))
+}
+
+fn parse_vec3(tokens: &mut SplitWhitespace) -> Result<Vec3, ObjErrorKind> {
+    Ok(Vec3::new(
+        parse_f64(tokens.next(), "x")?,
+        parse_f64(tokens.next(), "y")?,
+        parse_f64(tokens.next(), "z")?,
+    ))
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`, resolving 1-based and negative
+// (relative) indices to positions in the attribute arrays.
+fn parse_face_vertex(
+    vertex: &str,
+    data: &ObjData,
+) -> Result<(usize, Option<usize>, Option<usize>), ObjErrorKind> {
+    let resolve = |token: &str, len: usize| -> Result<usize, ObjErrorKind> {
+        let i: i64 = token
+            .parse()
+            .map_err(|_| ObjErrorKind::InvalidNumber(token.to_string()))?;
+        let index = if i > 0 { i - 1 } else { len as i64 + i };
+
+        if i == 0 || index < 0 || index >= len as i64 {
+            return Err(ObjErrorKind::IndexOutOfRange(vertex.to_string()));
+        }
+
+        Ok(index as usize)
+    };
+
+    let mut parts = vertex.split('/');
+    let v = resolve(parts.next().unwrap_or(""), data.positions.len())?;
+    let vt = match parts.next() {
+        Some(t) if !t.is_empty() => Some(resolve(t, data.uvs.len())?),
+        _ => None,
+    };
+    let vn = match parts.next() {
+        Some(n) if !n.is_empty() => Some(resolve(n, data.normals.len())?),
+        _ => None,
+    };
+
+    Ok((v, vt, vn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes `files` to a fresh directory and returns the path of the first.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rs-tracer-obj-{}-{}", std::process::id(), test));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir.join(files[0].0)
+    }
+
+    fn load_error(test: &str, files: &[(&str, &str)]) -> ObjError {
+        let path = write_files(test, files);
+        let mut world = HittableList::new();
+        load_obj(&path, &mut world).unwrap_err()
+    }
+
+    #[test]
+    fn loads_quad_as_one_mesh() {
+        let path = write_files(
+            "quad",
+            &[(
+                "quad.obj",
+                "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n",
+            )],
+        );
+        let mut world = HittableList::new();
+        load_obj(&path, &mut world).unwrap();
+        assert_eq!(world.objects.len(), 1);
+    }
+
+    #[test]
+    fn reports_line_of_invalid_number() {
+        let e = load_error("number", &[("a.obj", "# vertices\nv 0 0 0\nv 1 x 0\n")]);
+        assert_eq!(e.line, 3);
+        assert!(matches!(e.kind, ObjErrorKind::InvalidNumber(ref s) if s == "x"));
+    }
+
+    #[test]
+    fn reports_line_of_face_out_of_range() {
+        let e = load_error(
+            "range",
+            &[("a.obj", "v 0 0 0\nv 1 0 0\nv 1 1 0\n\nf 1 2 4\n")],
+        );
+        assert_eq!(e.line, 5);
+        assert!(matches!(e.kind, ObjErrorKind::IndexOutOfRange(ref s) if s == "4"));
+    }
+
+    #[test]
+    fn reports_line_of_degenerate_face() {
+        let e = load_error("degenerate", &[("a.obj", "v 0 0 0\nv 1 0 0\nf 1 2\n")]);
+        assert_eq!(e.line, 3);
+        assert!(matches!(e.kind, ObjErrorKind::DegenerateFace));
+    }
+
+    #[test]
+    fn reports_line_of_unknown_material() {
+        let e = load_error(
+            "material",
+            &[
+                ("a.obj", "mtllib a.mtl\nusemtl red\nusemtl blue\n"),
+                ("a.mtl", "newmtl red\nKd 1 0 0\n"),
+            ],
+        );
+        assert_eq!(e.line, 3);
+        assert!(matches!(e.kind, ObjErrorKind::UnknownMaterial(ref s) if s == "blue"));
+    }
+
+    #[test]
+    fn reports_mtl_errors_against_the_mtl_file() {
+        let e = load_error(
+            "mtl",
+            &[
+                ("a.obj", "mtllib a.mtl\n"),
+                ("a.mtl", "newmtl red\nKd 1 0\n"),
+            ],
+        );
+        assert_eq!(e.file.file_name().unwrap(), "a.mtl");
+        assert_eq!(e.line, 2);
+        assert!(matches!(e.kind, ObjErrorKind::MissingValue("z")));
+        assert!(e.to_string().ends_with("a.mtl:2: missing z"));
+    }
+
+    #[test]
+    fn reports_missing_file_without_line() {
+        let mut world = HittableList::new();
+        let e = load_obj("does/not/exist.obj", &mut world).unwrap_err();
+        assert_eq!(e.line, 0);
+        assert!(matches!(e.kind, ObjErrorKind::Io(_)));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+
+/// Orthonormal basis with `w` along a given direction.
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn build_from_w(n: Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0., 1., 0.)
+        } else {
+            Vec3::new(1., 0., 0.)
+        };
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
+
+        Onb { u, v, w }
+    }
+
+    /// Basis with `u` along the part of `tangent` perpendicular to `n`, so
+    /// the frame follows the surface. Falls back to `build_from_w` if the
+    /// tangent is missing or parallel to `n`.
+    pub fn build_from_w_and_tangent(n: Vec3, tangent: Vec3) -> Self {
+        let w = unit_vector(n);
+        let t = tangent - dot(tangent, w) * w;
+        if t.len_2() <= 1e-12 * tangent.len_2() {
+            return Onb::build_from_w(n);
+        }
+
+        let u = unit_vector(t);
+        let v = cross(w, u);
+
+        Onb { u, v, w }
+    }
+
+    /// Converts coordinates in this basis to world space.
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+
+    /// Converts a world space vector to coordinates in this basis.
+    pub fn to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(dot(a, self.u), dot(a, self.v), dot(a, self.w))
+    }
+}
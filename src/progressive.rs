@@ -0,0 +1,223 @@
+use crate::camera::Camera;
+use crate::film::Film;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::sample_pixel;
+use crate::vec3::Vec3;
+use crate::ImageConfig;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::prelude::ParallelSliceMut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Stops a progressive render from another thread. Clones share the same
+/// flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a progressive render returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// All `samples_per_pixel` of the image config were taken.
+    Finished,
+    Cancelled,
+    /// The time budget ran out.
+    OutOfTime,
+}
+
+/// State after a finished pass, handed to the pass callback.
+#[derive(Debug, Clone, Copy)]
+pub struct Pass {
+    /// Starts at 0.
+    pub index: u32,
+    /// Samples per pixel accumulated so far, including this pass.
+    pub samples_per_pixel: u32,
+    /// Samples per pixel the render is aiming for.
+    pub target_samples_per_pixel: u32,
+    /// Time since the render started.
+    pub elapsed: Duration,
+}
+
+pub struct ProgressiveResult {
+    /// Average of every completed pass.
+    pub film: Film,
+    pub samples_per_pixel: u32,
+    pub passes: u32,
+    pub elapsed: Duration,
+    pub stop_reason: StopReason,
+}
+
+/// Renders in passes of a few samples per pixel, averaging them into a film
+/// that can be shown after every pass, until `samples_per_pixel` of the image
+/// config are reached or the render is stopped.
+#[derive(Debug, Clone)]
+pub struct ProgressiveRenderer {
+    pub samples_per_pass: u32,
+    pub time_budget: Option<Duration>,
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for ProgressiveRenderer {
+    fn default() -> Self {
+        ProgressiveRenderer {
+            samples_per_pass: 1,
+            time_budget: None,
+            cancel: None,
+        }
+    }
+}
+
+impl ProgressiveRenderer {
+    pub fn new() -> Self {
+        ProgressiveRenderer::default()
+    }
+
+    pub fn samples_per_pass(mut self, samples_per_pass: u32) -> Self {
+        self.samples_per_pass = samples_per_pass.max(1);
+        self
+    }
+
+    /// Wall-clock time after which no new pass is started. A pass that would
+    /// end past the budget is abandoned, except for the first one, so there
+    /// is always an image to return.
+    pub fn time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Checked before every row, a cancelled pass is thrown away.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Like `render_with_lights`, calling `on_pass` with the current estimate
+    /// after every completed pass.
+    pub fn render(
+        &self,
+        world: impl Hittable,
+        lights: HittableList,
+        camera: Camera,
+        config: ImageConfig,
+        mut on_pass: impl FnMut(&Film, &Pass),
+    ) -> ProgressiveResult {
+        let start = Instant::now();
+        let deadline = self.time_budget.map(|budget| start + budget);
+        let samples_per_pass = self.samples_per_pass.max(1);
+
+        let mut film = Film::new(config.width, config.height);
+        film.tone_mapping = config.tone_mapping;
+
+        let mut sum = vec![Vec3::default(); film.pixels().len()];
+        let mut pass_sum = sum.clone();
+        let mut samples = 0;
+        let mut passes = 0;
+        let mut last_pass_time = Duration::ZERO;
+
+        let stop_reason = loop {
+            if samples >= config.samples_per_pixel {
+                break StopReason::Finished;
+            }
+            if self.is_cancelled() {
+                break StopReason::Cancelled;
+            }
+            // Don't start a pass that is expected to be thrown away.
+            if passes > 0
+                && deadline.is_some_and(|deadline| Instant::now() + last_pass_time > deadline)
+            {
+                break StopReason::OutOfTime;
+            }
+
+            let pass_samples = samples_per_pass.min(config.samples_per_pixel - samples);
+            let pass_start = Instant::now();
+            let abandoned = AtomicBool::new(false);
+
+            pass_sum
+                .par_chunks_exact_mut(config.width as usize)
+                .enumerate()
+                .for_each(|(y, row)| {
+                    let out_of_time =
+                        passes > 0 && deadline.is_some_and(|deadline| Instant::now() > deadline);
+                    if out_of_time || self.is_cancelled() || abandoned.load(Ordering::Relaxed) {
+                        abandoned.store(true, Ordering::Relaxed);
+                        return;
+                    }
+
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        *pixel = sample_pixel(
+                            x as u32,
+                            y as u32,
+                            samples..samples + pass_samples,
+                            &world,
+                            &lights,
+                            &camera,
+                            &config,
+                        );
+                    }
+                });
+
+            if abandoned.into_inner() {
+                break if self.is_cancelled() {
+                    StopReason::Cancelled
+                } else {
+                    StopReason::OutOfTime
+                };
+            }
+
+            last_pass_time = pass_start.elapsed();
+            samples += pass_samples;
+
+            for ((pixel, total), pass) in film
+                .pixels_mut()
+                .iter_mut()
+                .zip(sum.iter_mut())
+                .zip(pass_sum.iter())
+            {
+                *total += *pass;
+                *pixel = *total / samples as f64;
+            }
+
+            on_pass(
+                &film,
+                &Pass {
+                    index: passes,
+                    samples_per_pixel: samples,
+                    target_samples_per_pixel: config.samples_per_pixel,
+                    elapsed: start.elapsed(),
+                },
+            );
+            passes += 1;
+        };
+
+        ProgressiveResult {
+            film,
+            samples_per_pixel: samples,
+            passes,
+            elapsed: start.elapsed(),
+            stop_reason,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+    }
+}
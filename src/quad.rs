@@ -0,0 +1,267 @@
+use crate::aabb::{surrounding_box, Aabb};
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::material::MatType;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+use crate::INF;
+use std::sync::Arc;
+
+/// Parallelogram spanned by the edges `u` and `v` from the corner `q`.
+pub struct Quad {
+    pub q: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: MatType,
+    normal: Vec3,
+    d: f64,
+    w: Vec3,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, material: MatType) -> Self {
+        let n = cross(u, v);
+        let normal = unit_vector(n);
+
+        Quad {
+            q,
+            u,
+            v,
+            material,
+            normal,
+            d: dot(normal, q),
+            w: n / dot(n, n),
+            area: n.len(),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        let denom = dot(self.normal, r.dir);
+
+        // Rays parallel to the plane never hit it.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - dot(self.normal, r.orig)) / denom;
+        if t < t_min || t > t_max {
+            return false;
+        }
+
+        // Express the hit point in the (u, v) frame of the quad.
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = dot(self.w, cross(planar, self.v));
+        let beta = dot(self.w, cross(self.u, planar));
+
+        if !(0. ..=1.).contains(&alpha) || !(0. ..=1.).contains(&beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.tangent = self.u;
+        rec.material = &self.material;
+        rec.set_face_normal(r, self.normal);
+
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        let diag0 = Aabb::from_points(self.q, self.q + self.u + self.v);
+        let diag1 = Aabb::from_points(self.q + self.u, self.q + self.v);
+        *output_box = surrounding_box(diag0, diag1).pad(1e-4);
+        true
+    }
+
+    fn pdf_value(&self, origin: Vec3, dir: Vec3, time: f64) -> f64 {
+        area_light_pdf(self, self.area, origin, dir, time)
+    }
+
+    fn random(&self, origin: Vec3, _time: f64, sampler: &mut Sampler) -> Vec3 {
+        let r1 = sampler.next_f64();
+        let r2 = sampler.next_f64();
+        self.q + r1 * self.u + r2 * self.v - origin
+    }
+}
+
+// Converts uniform area sampling of a flat shape to a solid angle density.
+fn area_light_pdf(shape: &dyn Hittable, area: f64, origin: Vec3, dir: Vec3, time: f64) -> f64 {
+    let mut rec = HitRecord::new();
+    let r = Ray {
+        orig: origin,
+        dir,
+        time,
+    };
+    if !shape.hit(&r, 0.001, INF, &mut rec) {
+        return 0.;
+    }
+
+    let dist_2 = rec.t * rec.t * dir.len_2();
+    let cosine = (dot(dir, rec.norm) / dir.len()).abs();
+
+    dist_2 / (cosine * area)
+}
+
+/// Coordinate plane an `AaRect` lies in.
+#[derive(Clone, Copy)]
+pub enum Plane {
+    Xy,
+    Xz,
+    Yz,
+}
+
+impl Plane {
+    // In-plane axes followed by the normal axis.
+    fn axes(&self) -> (u32, u32, u32) {
+        match self {
+            Plane::Xy => (0, 1, 2),
+            Plane::Xz => (0, 2, 1),
+            Plane::Yz => (1, 2, 0),
+        }
+    }
+}
+
+/// Rectangle `[a0, a1] x [b0, b1]` in an axis-aligned plane at offset `k`
+/// along the remaining axis, facing that axis' positive direction.
+pub struct AaRect {
+    pub plane: Plane,
+    pub a0: f64,
+    pub a1: f64,
+    pub b0: f64,
+    pub b1: f64,
+    pub k: f64,
+    pub material: MatType,
+}
+
+impl AaRect {
+    /// The bounds on each axis may be given in either order.
+    pub fn new(
+        plane: Plane,
+        (a0, a1): (f64, f64),
+        (b0, b1): (f64, f64),
+        k: f64,
+        material: MatType,
+    ) -> Self {
+        AaRect {
+            plane,
+            a0: a0.min(a1),
+            a1: a0.max(a1),
+            b0: b0.min(b1),
+            b1: b0.max(b1),
+            k,
+            material,
+        }
+    }
+}
+
+impl Hittable for AaRect {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        let (a, b, n) = self.plane.axes();
+
+        let t = (self.k - r.orig[n]) / r.dir[n];
+        if !(t >= t_min && t <= t_max) {
+            return false;
+        }
+
+        let pa = r.orig[a] + t * r.dir[a];
+        let pb = r.orig[b] + t * r.dir[b];
+        if pa < self.a0 || pa > self.a1 || pb < self.b0 || pb > self.b1 {
+            return false;
+        }
+
+        let mut outward_norm = Vec3::default();
+        outward_norm[n] = 1.;
+        let mut tangent = Vec3::default();
+        tangent[a] = 1.;
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = (pa - self.a0) / (self.a1 - self.a0);
+        rec.v = (pb - self.b0) / (self.b1 - self.b0);
+        rec.tangent = tangent;
+        rec.material = &self.material;
+        rec.set_face_normal(r, outward_norm);
+
+        true
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        let (a, b, n) = self.plane.axes();
+        let mut min = Vec3::default();
+        let mut max = Vec3::default();
+
+        min[a] = self.a0;
+        max[a] = self.a1;
+        min[b] = self.b0;
+        max[b] = self.b1;
+        min[n] = self.k;
+        max[n] = self.k;
+
+        *output_box = Aabb::new(min, max).pad(1e-4);
+        true
+    }
+
+    fn pdf_value(&self, origin: Vec3, dir: Vec3, time: f64) -> f64 {
+        let area = (self.a1 - self.a0) * (self.b1 - self.b0);
+        area_light_pdf(self, area, origin, dir, time)
+    }
+
+    fn random(&self, origin: Vec3, _time: f64, sampler: &mut Sampler) -> Vec3 {
+        let (a, b, n) = self.plane.axes();
+        let mut p = Vec3::default();
+
+        p[a] = self.a0 + (self.a1 - self.a0) * sampler.next_f64();
+        p[b] = self.b0 + (self.b1 - self.b0) * sampler.next_f64();
+        p[n] = self.k;
+
+        p - origin
+    }
+}
+
+/// Axis-aligned box between two opposite corners, made of six quads.
+pub struct Cuboid {
+    sides: HittableList,
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3, material: MatType) -> Self {
+        let Aabb { min, max } = Aabb::from_points(a, b);
+
+        let dx = Vec3::new(max.x - min.x, 0., 0.);
+        let dy = Vec3::new(0., max.y - min.y, 0.);
+        let dz = Vec3::new(0., 0., max.z - min.z);
+
+        // Edges are ordered so every face normal points out of the box.
+        let mut sides = HittableList::new();
+        let faces = [
+            (Vec3::new(min.x, min.y, max.z), dx, dy),
+            (Vec3::new(max.x, min.y, max.z), -dz, dy),
+            (Vec3::new(max.x, min.y, min.z), -dx, dy),
+            (Vec3::new(min.x, min.y, min.z), dz, dy),
+            (Vec3::new(min.x, max.y, max.z), dx, -dz),
+            (Vec3::new(min.x, min.y, min.z), dx, dz),
+        ];
+        for (q, u, v) in faces {
+            sides.add(Arc::new(Quad::new(q, u, v, material.clone())));
+        }
+
+        Cuboid { sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        self.sides.hit(r, t_min, t_max, rec)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        self.sides.bounding_box(time0, time1, output_box)
+    }
+}
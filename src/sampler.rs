@@ -0,0 +1,73 @@
+use rand::{Error, RngCore};
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Small, fast random number generator (SplitMix64) threaded through the
+/// render path. Every pixel sample gets its own stream keyed by the global
+/// seed, the pixel and the sample index, so a render only depends on its seed
+/// and not on how the work is split between threads.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Sampler { state: mix(seed) }
+    }
+
+    /// Stream for sample `index` of pixel `(x, y)`.
+    pub fn for_pixel(seed: u64, x: u32, y: u32, index: u32) -> Self {
+        Sampler::from_keys(&[seed, ((x as u64) << 32) | y as u64, index as u64])
+    }
+
+    /// Stream determined by hashing every key in turn.
+    pub fn from_keys(keys: &[u64]) -> Self {
+        let state = keys
+            .iter()
+            .fold(0, |h: u64, &key| mix(h.wrapping_add(GOLDEN_GAMMA) ^ key));
+
+        Sampler { state }
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1. / (1u64 << 53) as f64)
+    }
+}
+
+impl RngCore for Sampler {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let z = splitmix64(self.state);
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        z
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// One SplitMix64 step from the state `x`, also a good 64-bit hash.
+pub(crate) fn splitmix64(x: u64) -> u64 {
+    mix(x.wrapping_add(GOLDEN_GAMMA))
+}
+
+// SplitMix64 output function.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
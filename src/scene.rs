@@ -0,0 +1,1115 @@
+use crate::camera::Camera;
+use crate::color::Background;
+use crate::hittable_list::HittableList;
+use crate::material::{Dielectric, Lambertian, MatType, Metal};
+use crate::sphere::Sphere;
+use crate::texture::Texture;
+use crate::tonemap::ToneMapping;
+use crate::vec3::{cross, Vec3};
+use crate::ImageConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Scene file contents: the image settings, the camera and a flat list of
+/// objects with their materials inlined. Vectors and colors are `[x, y, z]`
+/// arrays. The media and tone mapping of the image keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub image: ImageDescription,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+}
+
+/// The serializable part of `ImageConfig`. The aspect ratio follows from the
+/// width and height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageDescription {
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    pub background: BackgroundDescription,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", try_from = "RawBackground")]
+pub enum BackgroundDescription {
+    Black,
+    Solid { color: [f64; 3] },
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+}
+
+/// Arguments of `Camera::new`, plus the shutter interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub lookfrom: [f64; 3],
+    pub lookat: [f64; 3],
+    #[serde(default = "default_vup")]
+    pub vup: [f64; 3],
+    /// Vertical field of view in degrees.
+    pub vfov: f64,
+    #[serde(default)]
+    pub aperture: f64,
+    #[serde(default = "default_focus_dist")]
+    pub focus_dist: f64,
+    #[serde(default)]
+    pub shutter_open: f64,
+    #[serde(default = "default_shutter_close")]
+    pub shutter_close: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", try_from = "RawMaterial")]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: [f64; 3],
+    },
+    Metal {
+        albedo: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        index_refraction: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", try_from = "RawObject")]
+pub enum ObjectDescription {
+    /// A negative radius flips the normals, e.g. for the inside of a hollow
+    /// glass sphere.
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: MaterialDescription,
+    },
+    /// Moves from `center` at time 0 to `target` at time 1.
+    MovingSphere {
+        center: [f64; 3],
+        target: [f64; 3],
+        radius: f64,
+        material: MaterialDescription,
+    },
+}
+
+// The tagged enums are read through these flat structs instead of serde's
+// internally tagged representation, which buffers the fields and would lose
+// the path of a value with the wrong type. The conversions then check which
+// fields the tag allows.
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BackgroundType {
+    Black,
+    Solid,
+    Gradient,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBackground {
+    #[serde(rename = "type")]
+    kind: BackgroundType,
+    color: Option<[f64; 3]>,
+    bottom: Option<[f64; 3]>,
+    top: Option<[f64; 3]>,
+}
+
+impl TryFrom<RawBackground> for BackgroundDescription {
+    type Error = String;
+
+    fn try_from(raw: RawBackground) -> Result<Self, String> {
+        let mut fields = Fields::default();
+        let background = match raw.kind {
+            BackgroundType::Black => BackgroundDescription::Black,
+            BackgroundType::Solid => BackgroundDescription::Solid {
+                color: fields.required("color", raw.color)?,
+            },
+            BackgroundType::Gradient => BackgroundDescription::Gradient {
+                bottom: fields.required("bottom", raw.bottom)?,
+                top: fields.required("top", raw.top)?,
+            },
+        };
+
+        fields.check("color", raw.color.is_some())?;
+        fields.check("bottom", raw.bottom.is_some())?;
+        fields.check("top", raw.top.is_some())?;
+        Ok(background)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MaterialType {
+    Lambertian,
+    Metal,
+    Dielectric,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMaterial {
+    #[serde(rename = "type")]
+    kind: MaterialType,
+    albedo: Option<[f64; 3]>,
+    fuzz: Option<f64>,
+    index_refraction: Option<f64>,
+}
+
+impl TryFrom<RawMaterial> for MaterialDescription {
+    type Error = String;
+
+    fn try_from(raw: RawMaterial) -> Result<Self, String> {
+        let mut fields = Fields::default();
+        let material = match raw.kind {
+            MaterialType::Lambertian => MaterialDescription::Lambertian {
+                albedo: fields.required("albedo", raw.albedo)?,
+            },
+            MaterialType::Metal => MaterialDescription::Metal {
+                albedo: fields.required("albedo", raw.albedo)?,
+                fuzz: fields.optional("fuzz", raw.fuzz).unwrap_or(0.),
+            },
+            MaterialType::Dielectric => MaterialDescription::Dielectric {
+                index_refraction: fields.required("index_refraction", raw.index_refraction)?,
+            },
+        };
+
+        fields.check("albedo", raw.albedo.is_some())?;
+        fields.check("fuzz", raw.fuzz.is_some())?;
+        fields.check("index_refraction", raw.index_refraction.is_some())?;
+        Ok(material)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ObjectType {
+    Sphere,
+    MovingSphere,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawObject {
+    #[serde(rename = "type")]
+    kind: ObjectType,
+    center: Option<[f64; 3]>,
+    target: Option<[f64; 3]>,
+    radius: Option<f64>,
+    material: Option<MaterialDescription>,
+}
+
+impl TryFrom<RawObject> for ObjectDescription {
+    type Error = String;
+
+    fn try_from(raw: RawObject) -> Result<Self, String> {
+        let mut fields = Fields::default();
+        let center = fields.required("center", raw.center)?;
+        let radius = fields.required("radius", raw.radius)?;
+        let material = fields.required("material", raw.material)?;
+
+        let object = match raw.kind {
+            ObjectType::Sphere => ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            },
+            ObjectType::MovingSphere => ObjectDescription::MovingSphere {
+                center,
+                target: fields.required("target", raw.target)?,
+                radius,
+                material,
+            },
+        };
+
+        fields.check("target", raw.target.is_some())?;
+        Ok(object)
+    }
+}
+
+// Records the fields a variant used, so that any other field present can be
+// reported as unknown.
+#[derive(Default)]
+struct Fields(Vec<&'static str>);
+
+impl Fields {
+    fn required<T>(&mut self, name: &'static str, value: Option<T>) -> Result<T, String> {
+        self.0.push(name);
+        value.ok_or_else(|| format!("missing field `{}`", name))
+    }
+
+    fn optional<T>(&mut self, name: &'static str, value: Option<T>) -> Option<T> {
+        self.0.push(name);
+        value
+    }
+
+    fn check(&self, name: &str, present: bool) -> Result<(), String> {
+        if present && !self.0.contains(&name) {
+            return Err(format!("unknown field `{}`", name));
+        }
+        Ok(())
+    }
+}
+
+fn default_vup() -> [f64; 3] {
+    [0., 1., 0.]
+}
+
+fn default_focus_dist() -> f64 {
+    10.
+}
+
+fn default_shutter_close() -> f64 {
+    1.
+}
+
+impl Default for ImageDescription {
+    fn default() -> Self {
+        ImageDescription::from(&ImageConfig::default())
+    }
+}
+
+impl From<&ImageConfig> for ImageDescription {
+    fn from(config: &ImageConfig) -> Self {
+        ImageDescription {
+            width: config.width,
+            height: config.height,
+            samples_per_pixel: config.samples_per_pixel,
+            max_depth: config.max_depth,
+            background: BackgroundDescription::from(config.background),
+            seed: config.seed,
+        }
+    }
+}
+
+impl From<Background> for BackgroundDescription {
+    fn from(background: Background) -> Self {
+        match background {
+            Background::Black => BackgroundDescription::Black,
+            Background::Solid(c) => BackgroundDescription::Solid { color: array(c) },
+            Background::Gradient { bottom, top } => BackgroundDescription::Gradient {
+                bottom: array(bottom),
+                top: array(top),
+            },
+        }
+    }
+}
+
+impl From<BackgroundDescription> for Background {
+    fn from(background: BackgroundDescription) -> Self {
+        match background {
+            BackgroundDescription::Black => Background::Black,
+            BackgroundDescription::Solid { color } => Background::Solid(vec3(color)),
+            BackgroundDescription::Gradient { bottom, top } => Background::Gradient {
+                bottom: vec3(bottom),
+                top: vec3(top),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneErrorKind {
+    Io(std::io::Error),
+    Toml(Box<toml::de::Error>),
+    Json(serde_json::Error),
+    /// The file extension is neither `.toml` nor `.json`.
+    UnknownFormat,
+    /// A value that parsed but cannot be rendered.
+    Invalid(String),
+}
+
+/// Error while loading or validating a scene. `path` locates the offending
+/// value inside the scene, e.g. `objects[3].material.fuzz`, for parse errors
+/// as well as invalid values, and is empty for errors that concern the whole
+/// file.
+#[derive(Debug)]
+pub struct SceneError {
+    pub file: Option<PathBuf>,
+    pub path: String,
+    pub kind: SceneErrorKind,
+}
+
+impl SceneError {
+    fn invalid(path: impl Into<String>, message: impl Into<String>) -> Self {
+        SceneError {
+            file: None,
+            path: path.into(),
+            kind: SceneErrorKind::Invalid(message.into()),
+        }
+    }
+
+    fn whole_file(kind: SceneErrorKind) -> Self {
+        SceneError {
+            file: None,
+            path: String::new(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+
+        match &self.kind {
+            SceneErrorKind::Io(e) => write!(f, "{}", e),
+            SceneErrorKind::Toml(e) => write!(f, "{}", e),
+            SceneErrorKind::Json(e) => write!(f, "{}", e),
+            SceneErrorKind::UnknownFormat => {
+                write!(f, "unknown scene format, expected .toml or .json")
+            }
+            SceneErrorKind::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            SceneErrorKind::Io(e) => Some(e),
+            SceneErrorKind::Toml(e) => Some(e.as_ref()),
+            SceneErrorKind::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Everything needed to render a scene file.
+pub struct Scene {
+    pub world: HittableList,
+    /// The spheres making up `world`, for describing the scene again.
+    pub objects: Vec<Arc<Sphere>>,
+    pub camera: Camera,
+    pub config: ImageConfig,
+}
+
+impl Scene {
+    /// Converts the built scene back to a description, e.g. for saving it
+    /// after changing its objects or camera. See `SceneDescription::describe`.
+    pub fn describe(&self) -> Result<SceneDescription, SceneError> {
+        SceneDescription::describe(
+            self.objects.iter().map(|object| object.as_ref()),
+            &self.camera,
+            &self.config,
+        )
+    }
+}
+
+/// Loads and builds a scene, picking TOML or JSON from the file extension.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+
+    SceneDescription::load(path)?.build().map_err(|mut e| {
+        e.file = Some(path.to_path_buf());
+        e
+    })
+}
+
+enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Format, SceneError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(Format::Toml),
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(Format::Json),
+            _ => Err(SceneError::whole_file(SceneErrorKind::UnknownFormat)),
+        }
+    }
+}
+
+impl SceneDescription {
+    /// Reads a scene without validating it, picking TOML or JSON from the
+    /// file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let with_file = |mut e: SceneError| {
+            e.file = Some(path.to_path_buf());
+            e
+        };
+
+        let format = Format::from_path(path).map_err(with_file)?;
+        let text = fs::read_to_string(path)
+            .map_err(|e| with_file(SceneError::whole_file(SceneErrorKind::Io(e))))?;
+
+        match format {
+            Format::Toml => SceneDescription::from_toml(&text),
+            Format::Json => SceneDescription::from_json(&text),
+        }
+        .map_err(with_file)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, SceneError> {
+        serde_path_to_error::deserialize(toml::Deserializer::new(text))
+            .map_err(|e| at_path(e, |e| SceneErrorKind::Toml(Box::new(e))))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, SceneError> {
+        let mut deserializer = serde_json::Deserializer::from_str(text);
+        let description = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|e| at_path(e, SceneErrorKind::Json))?;
+
+        // Rejects trailing characters, like `serde_json::from_str`.
+        deserializer
+            .end()
+            .map_err(|e| SceneError::whole_file(SceneErrorKind::Json(e)))?;
+        Ok(description)
+    }
+
+    pub fn to_toml(&self) -> String {
+        // Only numbers, strings, arrays and tables, so this cannot fail.
+        toml::to_string(self).unwrap()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Writes the description in the format matching the file extension.
+    /// Scenes built in code can be saved by describing them first.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let with_file = |mut e: SceneError| {
+            e.file = Some(path.to_path_buf());
+            e
+        };
+
+        let text = match Format::from_path(path).map_err(with_file)? {
+            Format::Toml => self.to_toml(),
+            Format::Json => self.to_json(),
+        };
+
+        fs::write(path, text).map_err(|e| with_file(SceneError::whole_file(SceneErrorKind::Io(e))))
+    }
+
+    /// Checks every value that would make the renderer misbehave, reporting
+    /// the first one found.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        self.image.validate("image")?;
+        self.camera.validate("camera")?;
+
+        for (i, object) in self.objects.iter().enumerate() {
+            object.validate(&format!("objects[{}]", i))?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates the description and builds the world, camera and image
+    /// settings from it.
+    pub fn build(&self) -> Result<Scene, SceneError> {
+        self.validate()?;
+
+        let config = self.image.build();
+        let camera = self.camera.build(config.aspect_ratio);
+
+        let mut world = HittableList::new();
+        let mut objects = Vec::new();
+        for object in &self.objects {
+            let sphere = Arc::new(object.build());
+            world.add(sphere.clone());
+            objects.push(sphere);
+        }
+
+        Ok(Scene {
+            world,
+            objects,
+            camera,
+            config,
+        })
+    }
+
+    /// Describes spheres, a camera and image settings built in code. Only
+    /// what a scene file can hold is accepted: Lambertian, metal and glass
+    /// materials with solid colors, no media and the default tone mapping.
+    /// The error points at the first value that cannot be described.
+    pub fn describe<'a>(
+        objects: impl IntoIterator<Item = &'a Sphere>,
+        camera: &Camera,
+        config: &ImageConfig,
+    ) -> Result<Self, SceneError> {
+        if !config.media.is_empty() {
+            return Err(SceneError::invalid(
+                "image.media",
+                "cannot be stored in a scene file",
+            ));
+        }
+        if config.tone_mapping != ToneMapping::default() {
+            return Err(SceneError::invalid(
+                "image.tone_mapping",
+                "cannot be stored in a scene file",
+            ));
+        }
+
+        // The camera is rebuilt with the aspect ratio of the image.
+        let aspect_ratio = config.width as f64 / config.height as f64;
+        if (camera.aspect_ratio - aspect_ratio).abs() > 1e-9 * aspect_ratio {
+            return Err(SceneError::invalid(
+                "camera",
+                "aspect ratio must match the image width and height",
+            ));
+        }
+
+        let objects = objects
+            .into_iter()
+            .enumerate()
+            .map(|(i, sphere)| ObjectDescription::describe(sphere, &format!("objects[{}]", i)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(SceneDescription {
+            image: ImageDescription::from(config),
+            camera: CameraDescription::from(camera),
+            objects,
+        })
+    }
+}
+
+impl ImageDescription {
+    fn validate(&self, path: &str) -> Result<(), SceneError> {
+        // Pixel coordinates are normalized by `width - 1` and `height - 1`.
+        if self.width < 2 {
+            return Err(SceneError::invalid(
+                format!("{}.width", path),
+                "must be at least 2",
+            ));
+        }
+        if self.height < 2 {
+            return Err(SceneError::invalid(
+                format!("{}.height", path),
+                "must be at least 2",
+            ));
+        }
+        if self.samples_per_pixel == 0 {
+            return Err(SceneError::invalid(
+                format!("{}.samples_per_pixel", path),
+                "must be positive",
+            ));
+        }
+        if self.max_depth == 0 {
+            return Err(SceneError::invalid(
+                format!("{}.max_depth", path),
+                "must be positive",
+            ));
+        }
+
+        let path = format!("{}.background", path);
+        match &self.background {
+            BackgroundDescription::Black => Ok(()),
+            BackgroundDescription::Solid { color } => {
+                check_color(&format!("{}.color", path), *color)
+            }
+            BackgroundDescription::Gradient { bottom, top } => {
+                check_color(&format!("{}.bottom", path), *bottom)?;
+                check_color(&format!("{}.top", path), *top)
+            }
+        }
+    }
+
+    fn build(&self) -> ImageConfig {
+        ImageConfig {
+            aspect_ratio: self.width as f64 / self.height as f64,
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+            background: Background::from(self.background),
+            seed: self.seed,
+            ..Default::default()
+        }
+    }
+}
+
+impl CameraDescription {
+    fn validate(&self, path: &str) -> Result<(), SceneError> {
+        check_finite(&format!("{}.lookfrom", path), self.lookfrom)?;
+        check_finite(&format!("{}.lookat", path), self.lookat)?;
+        check_finite(&format!("{}.vup", path), self.vup)?;
+
+        let view = vec3(self.lookfrom) - vec3(self.lookat);
+        if view.near_zero() {
+            return Err(SceneError::invalid(
+                format!("{}.lookat", path),
+                "must differ from lookfrom",
+            ));
+        }
+        if cross(vec3(self.vup), view).near_zero() {
+            return Err(SceneError::invalid(
+                format!("{}.vup", path),
+                "must not be parallel to the view direction",
+            ));
+        }
+
+        if !(self.vfov > 0. && self.vfov < 180.) {
+            return Err(SceneError::invalid(
+                format!("{}.vfov", path),
+                "must be between 0 and 180 degrees",
+            ));
+        }
+        if !(self.aperture >= 0. && self.aperture.is_finite()) {
+            return Err(SceneError::invalid(
+                format!("{}.aperture", path),
+                "must be non-negative",
+            ));
+        }
+        if !(self.focus_dist > 0. && self.focus_dist.is_finite()) {
+            return Err(SceneError::invalid(
+                format!("{}.focus_dist", path),
+                "must be positive",
+            ));
+        }
+        if !self.shutter_open.is_finite() {
+            return Err(SceneError::invalid(
+                format!("{}.shutter_open", path),
+                "must be finite",
+            ));
+        }
+        if !(self.shutter_close >= self.shutter_open && self.shutter_close.is_finite()) {
+            return Err(SceneError::invalid(
+                format!("{}.shutter_close", path),
+                "must not be before shutter_open",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            vec3(self.lookfrom),
+            vec3(self.lookat),
+            vec3(self.vup),
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+        .with_shutter(self.shutter_open, self.shutter_close)
+    }
+}
+
+impl From<&Camera> for CameraDescription {
+    fn from(camera: &Camera) -> Self {
+        CameraDescription {
+            lookfrom: array(camera.origin),
+            lookat: array(camera.lookat),
+            vup: array(camera.vup),
+            vfov: camera.vfov,
+            aperture: camera.aperture,
+            focus_dist: camera.focus_dist,
+            shutter_open: camera.shutter_open,
+            shutter_close: camera.shutter_close,
+        }
+    }
+}
+
+impl MaterialDescription {
+    fn describe(material: &MatType, path: &str) -> Result<Self, SceneError> {
+        let solid = |albedo: &Arc<dyn Texture>| {
+            albedo.solid_color().map(array).ok_or_else(|| {
+                SceneError::invalid(format!("{}.albedo", path), "must be a solid color")
+            })
+        };
+
+        match material {
+            MatType::Lambertian(m) => Ok(MaterialDescription::Lambertian {
+                albedo: solid(&m.albedo)?,
+            }),
+            MatType::Metal(m) => Ok(MaterialDescription::Metal {
+                albedo: solid(&m.albedo)?,
+                fuzz: m.fuzz,
+            }),
+            MatType::Dielectric(m) => Ok(MaterialDescription::Dielectric {
+                index_refraction: m.index_refraction,
+            }),
+            _ => Err(SceneError::invalid(
+                path,
+                "cannot be stored in a scene file",
+            )),
+        }
+    }
+
+    fn validate(&self, path: &str) -> Result<(), SceneError> {
+        match self {
+            MaterialDescription::Lambertian { albedo } => {
+                check_color(&format!("{}.albedo", path), *albedo)
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                check_color(&format!("{}.albedo", path), *albedo)?;
+                if !(0. ..=1.).contains(fuzz) {
+                    return Err(SceneError::invalid(
+                        format!("{}.fuzz", path),
+                        "must be between 0 and 1",
+                    ));
+                }
+                Ok(())
+            }
+            MaterialDescription::Dielectric { index_refraction } => {
+                if !(*index_refraction > 0. && index_refraction.is_finite()) {
+                    return Err(SceneError::invalid(
+                        format!("{}.index_refraction", path),
+                        "must be positive",
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn build(&self) -> MatType {
+        match self {
+            MaterialDescription::Lambertian { albedo } => {
+                MatType::Lambertian(Lambertian::new(vec3(*albedo)))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                MatType::Metal(Metal::new(vec3(*albedo), *fuzz))
+            }
+            MaterialDescription::Dielectric { index_refraction } => {
+                MatType::Dielectric(Dielectric {
+                    index_refraction: *index_refraction,
+                })
+            }
+        }
+    }
+}
+
+impl ObjectDescription {
+    fn describe(sphere: &Sphere, path: &str) -> Result<Self, SceneError> {
+        let material =
+            MaterialDescription::describe(&sphere.material, &format!("{}.material", path))?;
+
+        Ok(if sphere.moving {
+            ObjectDescription::MovingSphere {
+                center: array(sphere.center),
+                target: array(sphere.center + sphere.velocity),
+                radius: sphere.rad,
+                material,
+            }
+        } else {
+            ObjectDescription::Sphere {
+                center: array(sphere.center),
+                radius: sphere.rad,
+                material,
+            }
+        })
+    }
+
+    fn validate(&self, path: &str) -> Result<(), SceneError> {
+        let (radius, material) = match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                check_finite(&format!("{}.center", path), *center)?;
+                (radius, material)
+            }
+            ObjectDescription::MovingSphere {
+                center,
+                target,
+                radius,
+                material,
+            } => {
+                check_finite(&format!("{}.center", path), *center)?;
+                check_finite(&format!("{}.target", path), *target)?;
+                (radius, material)
+            }
+        };
+
+        if !(*radius != 0. && radius.is_finite()) {
+            return Err(SceneError::invalid(
+                format!("{}.radius", path),
+                "must be non-zero",
+            ));
+        }
+
+        material.validate(&format!("{}.material", path))
+    }
+
+    fn build(&self) -> Sphere {
+        match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Sphere::new(vec3(*center), *radius, material.build()),
+            ObjectDescription::MovingSphere {
+                center,
+                target,
+                radius,
+                material,
+            } => Sphere::new_moving(vec3(*center), vec3(*target), *radius, material.build()),
+        }
+    }
+}
+
+// Keeps the location of a parse error inside the scene as the error path.
+fn at_path<E>(
+    error: serde_path_to_error::Error<E>,
+    kind: impl FnOnce(E) -> SceneErrorKind,
+) -> SceneError {
+    let path = error.path().to_string();
+    let mut error = SceneError::whole_file(kind(error.into_inner()));
+    // The path of the whole file is printed as ".".
+    if path != "." {
+        error.path = path;
+    }
+    error
+}
+
+fn check_finite(path: &str, v: [f64; 3]) -> Result<(), SceneError> {
+    if v.iter().all(|c| c.is_finite()) {
+        Ok(())
+    } else {
+        Err(SceneError::invalid(path, "must be finite"))
+    }
+}
+
+fn check_color(path: &str, c: [f64; 3]) -> Result<(), SceneError> {
+    if c.iter().all(|c| *c >= 0. && c.is_finite()) {
+        Ok(())
+    } else {
+        Err(SceneError::invalid(path, "must be finite and non-negative"))
+    }
+}
+
+fn vec3(a: [f64; 3]) -> Vec3 {
+    Vec3::new(a[0], a[1], a[2])
+}
+
+fn array(v: Vec3) -> [f64; 3] {
+    [v.x, v.y, v.z]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::DiffuseLight;
+
+    const SCENE: &str = r#"
+[image]
+width = 40
+height = 20
+
+[camera]
+lookfrom = [0.0, 0.0, 5.0]
+lookat = [0.0, 0.0, 0.0]
+vfov = 30.0
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+
+[[objects]]
+type = "moving_sphere"
+center = [2.0, 0.0, 0.0]
+target = [2.0, 1.0, 0.0]
+radius = 0.5
+material = { type = "metal", albedo = [0.8, 0.8, 0.8], fuzz = 0.1 }
+"#;
+
+    fn scene() -> SceneDescription {
+        SceneDescription::from_toml(SCENE).unwrap()
+    }
+
+    fn set_fuzz(description: &mut SceneDescription, value: f64) {
+        match &mut description.objects[1] {
+            ObjectDescription::MovingSphere {
+                material: MaterialDescription::Metal { fuzz, .. },
+                ..
+            } => *fuzz = value,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_toml_and_json() {
+        let description = scene();
+        assert!(description.build().is_ok());
+        assert_eq!(
+            SceneDescription::from_toml(&description.to_toml()).unwrap(),
+            description
+        );
+        assert_eq!(
+            SceneDescription::from_json(&description.to_json()).unwrap(),
+            description
+        );
+    }
+
+    fn film_bits(film: &crate::film::Film) -> Vec<[u64; 3]> {
+        film.pixels()
+            .iter()
+            .map(|p| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+            .collect()
+    }
+
+    #[test]
+    fn built_scene_round_trips_through_a_scene_file() {
+        let spheres = vec![
+            Sphere::new(
+                Vec3::new(0., -100.5, -1.),
+                100.,
+                MatType::Lambertian(Lambertian::new(Vec3::new(0.5, 0.75, 0.25))),
+            ),
+            Sphere::new(
+                Vec3::new(-1., 0., -1.),
+                -0.5,
+                MatType::Dielectric(Dielectric {
+                    index_refraction: 1.5,
+                }),
+            ),
+            Sphere::new_moving(
+                Vec3::new(1., 0., -1.),
+                Vec3::new(1., 0.5, -1.),
+                0.5,
+                MatType::Metal(Metal::new(Vec3::new(0.75, 0.5, 0.25), 0.25)),
+            ),
+        ];
+        let config = ImageConfig {
+            aspect_ratio: 2.,
+            width: 16,
+            height: 8,
+            samples_per_pixel: 4,
+            max_depth: 6,
+            background: Background::Solid(Vec3::new(0.5, 0.5, 1.)),
+            seed: 3,
+            ..ImageConfig::default()
+        };
+        let camera = || {
+            Camera::new(
+                Vec3::new(0., 1., 2.),
+                Vec3::new(0., 0., -1.),
+                Vec3::new(0., 1., 0.),
+                50.,
+                2.,
+                0.125,
+                3.,
+            )
+            .with_shutter(0., 0.5)
+        };
+
+        let description = SceneDescription::describe(&spheres, &camera(), &config).unwrap();
+        let reloaded = SceneDescription::from_toml(&description.to_toml())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(reloaded.describe().unwrap(), description);
+
+        let mut world = HittableList::new();
+        for sphere in spheres {
+            world.add(Arc::new(sphere));
+        }
+        let original = crate::render(world, camera(), config);
+        let rendered = crate::render(reloaded.world, reloaded.camera, reloaded.config);
+        assert_eq!(film_bits(&rendered), film_bits(&original));
+    }
+
+    #[test]
+    fn describe_reports_what_a_scene_file_cannot_hold() {
+        let camera = Camera::new(
+            Vec3::new(0., 0., 1.),
+            Vec3::new(0., 0., 0.),
+            Vec3::new(0., 1., 0.),
+            40.,
+            16. / 9.,
+            0.,
+            1.,
+        );
+        let light = Sphere::new(
+            Vec3::new(0., 0., 0.),
+            1.,
+            MatType::DiffuseLight(DiffuseLight::new(Vec3::new(1., 1., 1.))),
+        );
+        let config = ImageConfig::default();
+
+        let e = SceneDescription::describe([&light], &camera, &config).unwrap_err();
+        assert_eq!(e.path, "objects[0].material");
+
+        let e = SceneDescription::describe(
+            [],
+            &camera,
+            &ImageConfig {
+                width: 100,
+                height: 100,
+                ..ImageConfig::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(e.path, "camera");
+    }
+
+    #[test]
+    fn validation_reports_value_path() {
+        let mut description = scene();
+        description.image.height = 1;
+        let e = description.validate().unwrap_err();
+        assert_eq!(e.path, "image.height");
+
+        let mut description = scene();
+        description.camera.lookat = description.camera.lookfrom;
+        assert_eq!(description.validate().unwrap_err().path, "camera.lookat");
+
+        let mut description = scene();
+        set_fuzz(&mut description, 2.);
+        let e = description.build().err().unwrap();
+        assert_eq!(e.path, "objects[1].material.fuzz");
+        assert_eq!(
+            e.to_string(),
+            "objects[1].material.fuzz: must be between 0 and 1"
+        );
+    }
+
+    #[test]
+    fn parse_errors_report_value_path() {
+        let toml = SCENE.replace("fuzz = 0.1", "fuzz = \"high\"");
+        let e = SceneDescription::from_toml(&toml).unwrap_err();
+        assert_eq!(e.path, "objects[1].material.fuzz");
+        assert!(matches!(e.kind, SceneErrorKind::Toml(_)));
+
+        let mut json = scene().to_json();
+        json = json.replace("\"vfov\": 30.0", "\"vfov\": [30.0]");
+        let e = SceneDescription::from_json(&json).unwrap_err();
+        assert_eq!(e.path, "camera.vfov");
+        assert!(matches!(e.kind, SceneErrorKind::Json(_)));
+    }
+
+    #[test]
+    fn parse_errors_name_missing_and_unknown_fields() {
+        let toml = SCENE.replace("radius = 0.5\n", "");
+        let e = SceneDescription::from_toml(&toml).unwrap_err();
+        assert_eq!(e.path, "objects[1]");
+        assert!(e.to_string().contains("missing field `radius`"));
+
+        let toml = SCENE.replace("radius = 0.5", "radius = 0.5\nmass = 2.0");
+        let e = SceneDescription::from_toml(&toml).unwrap_err();
+        assert_eq!(e.path, "objects[1].mass");
+        assert!(e.to_string().contains("unknown field `mass`"));
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        let e = SceneDescription::load("scene.yaml").unwrap_err();
+        assert!(matches!(e.kind, SceneErrorKind::UnknownFormat));
+        assert_eq!(e.file.as_deref(), Some(Path::new("scene.yaml")));
+    }
+}
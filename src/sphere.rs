@@ -1,7 +1,11 @@
+use crate::aabb::{surrounding_box, Aabb};
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::MatType;
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{dot, Vec3};
+use crate::{INF, PI};
 
 pub struct Sphere {
     pub center: Vec3,
@@ -35,10 +39,19 @@ impl Sphere {
     fn sphere_center(&self, time: f64) -> Vec3 {
         self.center + time * self.velocity
     }
+
+    /// Maps a point on the unit sphere to `(u, v)` in `[0, 1]`, with `u`
+    /// running around the y axis starting from -x and `v` from -y to +y.
+    pub fn get_sphere_uv(p: Vec3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+
+        (phi / (2. * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
         let center = if self.moving {
             self.sphere_center(r.time)
         } else {
@@ -69,10 +82,71 @@ impl Hittable for Sphere {
 
         rec.t = root;
         rec.p = r.at(rec.t);
-        rec.material = self.material;
-        let outward_norm: Vec3 = (rec.p - self.center) / self.rad;
+        rec.material = &self.material;
+        let outward_norm: Vec3 = (rec.p - center) / self.rad;
         rec.set_face_normal(r, outward_norm);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(outward_norm);
+        // Derivative of the point with respect to `u`, around the y axis.
+        rec.tangent = Vec3::new(outward_norm.z, 0., -outward_norm.x);
+
+        true
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        // Hollow spheres use a negative radius, so bound by its magnitude.
+        let r = Vec3::new(self.rad.abs(), self.rad.abs(), self.rad.abs());
+
+        *output_box = if self.moving {
+            let c0 = self.sphere_center(time0);
+            let c1 = self.sphere_center(time1);
+            surrounding_box(Aabb::new(c0 - r, c0 + r), Aabb::new(c1 - r, c1 + r))
+        } else {
+            Aabb::new(self.center - r, self.center + r)
+        };
 
         true
     }
+
+    fn pdf_value(&self, origin: Vec3, dir: Vec3, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray {
+            orig: origin,
+            dir,
+            time,
+        };
+        if !self.hit(&r, 0.001, INF, &mut rec) {
+            return 0.;
+        }
+
+        let dist_2 = (self.sphere_center(time) - origin).len_2();
+        let rad_2 = self.rad * self.rad;
+        if dist_2 <= rad_2 {
+            // From inside, every direction hits the sphere.
+            return 1. / (4. * PI);
+        }
+
+        let cos_theta_max = (1. - rad_2 / dist_2).sqrt();
+        let solid_angle = 2. * PI * (1. - cos_theta_max);
+
+        1. / solid_angle
+    }
+
+    fn random(&self, origin: Vec3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        let direction = self.sphere_center(time) - origin;
+        let dist_2 = direction.len_2();
+        let rad_2 = self.rad * self.rad;
+        if dist_2 <= rad_2 {
+            return Vec3::random_unit(sampler);
+        }
+
+        // Uniform direction inside the cone subtended by the sphere.
+        let r1 = sampler.next_f64();
+        let r2 = sampler.next_f64();
+        let z = 1. + r2 * ((1. - rad_2 / dist_2).sqrt() - 1.);
+        let phi = 2. * PI * r1;
+        let x = phi.cos() * (1. - z * z).sqrt();
+        let y = phi.sin() * (1. - z * z).sqrt();
+
+        Onb::build_from_w(direction).local(Vec3::new(x, y, z))
+    }
 }
@@ -0,0 +1,254 @@
+use crate::noise::{Perlin, Worley, WorleyFeature};
+use crate::vec3::Vec3;
+use image::{DynamicImage, ImageResult};
+use std::path::Path;
+use std::sync::Arc;
+
+pub trait Texture: Send + Sync {
+    /// Color at surface coordinates `(u, v)` and point `p`.
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+
+    /// The color if it is the same everywhere, which is what scene files can
+    /// describe.
+    fn solid_color(&self) -> Option<Vec3> {
+        None
+    }
+}
+
+pub struct SolidColor {
+    pub color: Vec3,
+}
+
+impl SolidColor {
+    pub fn new(color: Vec3) -> Self {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+        self.color
+    }
+
+    fn solid_color(&self) -> Option<Vec3> {
+        Some(self.color)
+    }
+}
+
+/// Alternates between two textures on a 3D grid of cubes `scale` wide.
+pub struct CheckerTexture {
+    pub inv_scale: f64,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        CheckerTexture {
+            inv_scale: 1. / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Vec3, odd: Vec3) -> Self {
+        CheckerTexture::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Image looked up by `(u, v)` with nearest-neighbour filtering. Texels are
+/// decoded from sRGB to linear color when the image is loaded.
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    texels: Vec<Vec3>,
+}
+
+impl ImageTexture {
+    pub fn load(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(ImageTexture::from_image(&image::open(path)?))
+    }
+
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let img = img.to_rgb8();
+        let lut: Vec<f64> = (0..=255).map(|c| srgb_to_linear(c as f64 / 255.)).collect();
+
+        let texels = img
+            .pixels()
+            .map(|px| {
+                Vec3::new(
+                    lut[px[0] as usize],
+                    lut[px[1] as usize],
+                    lut[px[2] as usize],
+                )
+            })
+            .collect();
+
+        ImageTexture {
+            width: img.width(),
+            height: img.height(),
+            texels,
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        if self.texels.is_empty() {
+            // Debug cyan for missing image data.
+            return Vec3::new(0., 1., 1.);
+        }
+
+        // Image rows run top to bottom, v runs bottom to top.
+        let u = u.clamp(0., 1.);
+        let v = 1. - v.clamp(0., 1.);
+
+        let i = ((u * self.width as f64) as u32).min(self.width - 1);
+        let j = ((v * self.height as f64) as u32).min(self.height - 1);
+
+        self.texels[(j * self.width + i) as usize]
+    }
+}
+
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Perlin noise remapped to `[0, 1]` and scaled into `color`.
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub color: Vec3,
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, scale: f64) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(seed),
+            scale,
+            color: Vec3::new(1., 1., 1.),
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        0.5 * (1. + self.noise.noise(self.scale * p)) * self.color
+    }
+}
+
+/// Veins along z, phase-shifted by turbulence.
+pub struct MarbleTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub turbulence_depth: u32,
+    pub color: Vec3,
+}
+
+impl MarbleTexture {
+    pub fn new(seed: u64, scale: f64) -> Self {
+        MarbleTexture {
+            noise: Perlin::new(seed),
+            scale,
+            turbulence_depth: 7,
+            color: Vec3::new(1., 1., 1.),
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let turb = self.noise.turbulence(p, self.turbulence_depth);
+        0.5 * (1. + (self.scale * p.z + 10. * turb).sin()) * self.color
+    }
+}
+
+/// Growth rings around the y axis, warped by fBm.
+pub struct WoodTexture {
+    pub noise: Perlin,
+    /// Rings per unit distance from the axis.
+    pub rings: f64,
+    pub light: Vec3,
+    pub dark: Vec3,
+}
+
+impl WoodTexture {
+    pub fn new(seed: u64, rings: f64) -> Self {
+        WoodTexture {
+            noise: Perlin::new(seed),
+            rings,
+            light: Vec3::new(0.75, 0.55, 0.33),
+            dark: Vec3::new(0.45, 0.28, 0.14),
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let r = (p.x * p.x + p.z * p.z).sqrt();
+        let warp = 0.25 * self.noise.fbm(p, 4, 2., 0.5);
+        let ring = (self.rings * (r + warp)).fract();
+
+        // Sharpen the rings so the dark latewood stays thin.
+        let t = ring.powi(3);
+        (1. - t) * self.light + t * self.dark
+    }
+}
+
+/// Voronoi cells, either shaded by a Worley distance or flat per cell.
+pub struct CellTexture {
+    pub worley: Worley,
+    pub scale: f64,
+    pub feature: WorleyFeature,
+    pub color: Vec3,
+    /// Give every cell its own random shade instead of a distance gradient.
+    pub flat: bool,
+}
+
+impl CellTexture {
+    pub fn new(seed: u64, scale: f64, feature: WorleyFeature) -> Self {
+        CellTexture {
+            worley: Worley::new(seed),
+            scale,
+            feature,
+            color: Vec3::new(1., 1., 1.),
+            flat: false,
+        }
+    }
+}
+
+impl Texture for CellTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let sample = self.worley.sample(self.scale * p);
+
+        let shade = if self.flat {
+            sample.cell_id
+        } else {
+            sample.feature(self.feature).min(1.)
+        };
+
+        shade * self.color
+    }
+}
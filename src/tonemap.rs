@@ -0,0 +1,229 @@
+use crate::vec3::Vec3;
+
+/// Curve compressing scene radiance into the displayable `[0, 1]` range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapOperator {
+    /// No compression, values above 1 are clipped.
+    Clamp,
+    /// `c / (1 + c)`.
+    Reinhard,
+    /// Reinhard with `white` mapped to exactly 1.
+    ReinhardExtended { white: f64 },
+    /// Narkowicz' fit of the ACES filmic reference rendering transform.
+    Aces,
+    /// John Hable's Uncharted 2 filmic curve.
+    Hable,
+    /// Polynomial approximation of the AgX base transform.
+    AgX,
+}
+
+/// Encoding from linear display values to the values stored in the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    Linear,
+    /// The exact piecewise sRGB curve.
+    Srgb,
+    /// Pure power curve, `Gamma(2.)` reproduces the old square root output.
+    Gamma(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// Scale applied before the operator, in stops.
+    pub exposure: f64,
+    pub transfer: TransferFunction,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            operator: ToneMapOperator::Clamp,
+            exposure: 0.,
+            transfer: TransferFunction::Srgb,
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Maps linear radiance to encoded display values in `[0, 1]`.
+    pub fn apply(&self, c: Vec3) -> Vec3 {
+        let c = c * 2f64.powf(self.exposure);
+
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => c,
+            ToneMapOperator::Reinhard => per_channel(c, |x| x / (1. + x)),
+            ToneMapOperator::ReinhardExtended { white } => {
+                let w2 = white * white;
+                per_channel(c, |x| x * (1. + x / w2) / (1. + x))
+            }
+            ToneMapOperator::Aces => per_channel(c, aces),
+            ToneMapOperator::Hable => {
+                // The curve is tuned for twice the input and a white point of 11.2.
+                let white_scale = 1. / hable_partial(11.2);
+                per_channel(c, |x| hable_partial(2. * x) * white_scale)
+            }
+            ToneMapOperator::AgX => agx(c),
+        };
+
+        per_channel(mapped, |x| self.transfer.encode(x.clamp(0., 1.)))
+    }
+
+    pub fn to_rgb8(&self, c: Vec3) -> [u8; 3] {
+        let c = self.apply(c);
+        [quantize(c.x), quantize(c.y), quantize(c.z)]
+    }
+}
+
+impl TransferFunction {
+    pub fn encode(&self, x: f64) -> f64 {
+        match *self {
+            TransferFunction::Linear => x,
+            TransferFunction::Srgb => linear_to_srgb(x),
+            TransferFunction::Gamma(g) => x.powf(1. / g),
+        }
+    }
+}
+
+pub fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn quantize(x: f64) -> u8 {
+    (x.clamp(0., 1.) * 255. + 0.5) as u8
+}
+
+fn per_channel(c: Vec3, f: impl Fn(f64) -> f64) -> Vec3 {
+    Vec3::new(f(c.x.max(0.)), f(c.y.max(0.)), f(c.z.max(0.)))
+}
+
+fn aces(x: f64) -> f64 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    (x * (a * x + b)) / (x * (c * x + d) + e)
+}
+
+fn hable_partial(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.5, 0.1, 0.2, 0.02, 0.3);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+// Matrix given by its columns, as in the reference GLSL.
+fn mul_columns(m: [[f64; 3]; 3], v: Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * v.x + m[1][0] * v.y + m[2][0] * v.z,
+        m[0][1] * v.x + m[1][1] * v.y + m[2][1] * v.z,
+        m[0][2] * v.x + m[1][2] * v.y + m[2][2] * v.z,
+    )
+}
+
+fn agx(c: Vec3) -> Vec3 {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0423282422610123, 0.0423756549057051],
+        [0.0784335999999992, 0.878468636469772, 0.0784336],
+        [0.0792237451477643, 0.0791661274605434, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0528968517574562, -0.0529716355144438],
+        [-0.0980208811401368, 1.15190312990417, -0.0980434501171241],
+        [-0.0990297440797205, -0.0989611768448433, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    // Encode in log2 around middle grey, then apply the sigmoid contrast curve.
+    let v = mul_columns(INSET, c);
+    let v = per_channel(v, |x| {
+        let ev = x.max(1e-10).log2().clamp(MIN_EV, MAX_EV);
+        let x = (ev - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+
+    // The curve outputs display-encoded values, bring them back to linear so
+    // the transfer function can be applied like for the other operators.
+    per_channel(mul_columns(OUTSET, v), |x| x.powf(2.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [ToneMapOperator; 6] = [
+        ToneMapOperator::Clamp,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::ReinhardExtended { white: 4. },
+        ToneMapOperator::Aces,
+        ToneMapOperator::Hable,
+        ToneMapOperator::AgX,
+    ];
+
+    fn linear(operator: ToneMapOperator) -> ToneMapping {
+        ToneMapping {
+            operator,
+            exposure: 0.,
+            transfer: TransferFunction::Linear,
+        }
+    }
+
+    fn grey(x: f64) -> Vec3 {
+        Vec3::new(x, x, x)
+    }
+
+    #[test]
+    fn curves_map_black_to_black_and_white_points_to_one() {
+        for operator in OPERATORS {
+            let c = linear(operator).apply(grey(0.));
+            assert!(c.x.abs() < 1e-3, "{:?} maps black to {}", operator, c.x);
+        }
+
+        assert_eq!(linear(ToneMapOperator::Clamp).apply(grey(1.)).x, 1.);
+        assert_eq!(linear(ToneMapOperator::Clamp).apply(grey(0.25)).x, 0.25);
+        assert_eq!(linear(ToneMapOperator::Reinhard).apply(grey(1.)).x, 0.5);
+        let white = linear(ToneMapOperator::ReinhardExtended { white: 4. }).apply(grey(4.));
+        assert!((white.x - 1.).abs() < 1e-12);
+        let white = linear(ToneMapOperator::Hable).apply(grey(5.6));
+        assert!((white.x - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn curves_are_monotone_and_stay_in_range() {
+        for operator in OPERATORS {
+            let mapping = linear(operator);
+            let mut last = 0.;
+            for i in 0..=400 {
+                let x = 2f64.powf(-10. + i as f64 * 0.05);
+                let y = mapping.apply(grey(x)).x;
+                assert!((0. ..=1.).contains(&y));
+                assert!(y >= last - 1e-12, "{:?} decreases at {}", operator, x);
+                last = y;
+            }
+        }
+    }
+
+    #[test]
+    fn exposure_is_in_stops() {
+        let mapping = ToneMapping {
+            exposure: 2.,
+            ..linear(ToneMapOperator::Clamp)
+        };
+        assert_eq!(mapping.apply(grey(0.125)).x, 0.5);
+    }
+
+    #[test]
+    fn srgb_curve_is_continuous_and_fixes_its_endpoints() {
+        assert_eq!(linear_to_srgb(0.), 0.);
+        assert!((linear_to_srgb(1.) - 1.).abs() < 1e-12);
+
+        // Both pieces meet at the threshold.
+        let knee = 0.0031308;
+        assert!((12.92 * knee - (1.055 * f64::powf(knee, 1. / 2.4) - 0.055)).abs() < 1e-6);
+        assert!((linear_to_srgb(0.214041140) - 0.5).abs() < 1e-6);
+    }
+}
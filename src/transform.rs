@@ -0,0 +1,830 @@
+use crate::aabb::{surrounding_box, Aabb};
+use crate::deg_to_rad;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{dot, unit_vector, Vec3};
+use std::ops;
+use std::sync::Arc;
+
+type Matrix = [[f64; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
+/// Affine transform as a 4x4 matrix, stored together with its inverse.
+/// Composing with `*` applies the right-hand side first, `then` reads in the
+/// order the transforms are applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: Matrix,
+    inv: Matrix,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            m: IDENTITY,
+            inv: IDENTITY,
+        }
+    }
+
+    /// Returns `None` if the matrix cannot be inverted.
+    pub fn from_matrix(m: [[f64; 4]; 4]) -> Option<Self> {
+        Some(Transform {
+            m,
+            inv: invert(&m)?,
+        })
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut m = IDENTITY;
+        let mut inv = IDENTITY;
+        for a in 0..3 {
+            m[a][3] = offset[a as u32];
+            inv[a][3] = -offset[a as u32];
+        }
+
+        Transform { m, inv }
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        if factors.x == 0. || factors.y == 0. || factors.z == 0. {
+            panic!("Cannot scale by zero!");
+        }
+
+        let mut m = IDENTITY;
+        let mut inv = IDENTITY;
+        for a in 0..3 {
+            m[a][a] = factors[a as u32];
+            inv[a][a] = 1. / factors[a as u32];
+        }
+
+        Transform { m, inv }
+    }
+
+    pub fn rotate_x(deg: f64) -> Self {
+        Transform::rotate(Vec3::new(1., 0., 0.), deg)
+    }
+
+    pub fn rotate_y(deg: f64) -> Self {
+        Transform::rotate(Vec3::new(0., 1., 0.), deg)
+    }
+
+    pub fn rotate_z(deg: f64) -> Self {
+        Transform::rotate(Vec3::new(0., 0., 1.), deg)
+    }
+
+    /// Counter-clockwise rotation by `deg` degrees about `axis`, looking
+    /// down the axis towards the origin.
+    pub fn rotate(axis: Vec3, deg: f64) -> Self {
+        let a = unit_vector(axis);
+        let (sin, cos) = deg_to_rad(deg).sin_cos();
+        let t = 1. - cos;
+
+        let mut m = IDENTITY;
+        m[0] = [
+            t * a.x * a.x + cos,
+            t * a.x * a.y - sin * a.z,
+            t * a.x * a.z + sin * a.y,
+            0.,
+        ];
+        m[1] = [
+            t * a.x * a.y + sin * a.z,
+            t * a.y * a.y + cos,
+            t * a.y * a.z - sin * a.x,
+            0.,
+        ];
+        m[2] = [
+            t * a.x * a.z - sin * a.y,
+            t * a.y * a.z + sin * a.x,
+            t * a.z * a.z + cos,
+            0.,
+        ];
+
+        Transform::from_rotation(m)
+    }
+
+    // Rotations are orthogonal, so the inverse is the transpose.
+    fn from_rotation(m: Matrix) -> Self {
+        Transform {
+            m,
+            inv: transpose(&m),
+        }
+    }
+
+    /// Applies `self` first, then `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        *next * *self
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    pub fn matrix(&self) -> [[f64; 4]; 4] {
+        self.m
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        let v = self.vector(p);
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        let p = v + Vec3::new(m[0][3], m[1][3], m[2][3]);
+
+        if w == 1. {
+            p
+        } else {
+            p / w
+        }
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Normals go through the inverse transpose so they stay perpendicular
+    /// to the transformed surface. The result is not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let inv = &self.inv;
+        Vec3::new(
+            inv[0][0] * n.x + inv[1][0] * n.y + inv[2][0] * n.z,
+            inv[0][1] * n.x + inv[1][1] * n.y + inv[2][1] * n.z,
+            inv[0][2] * n.x + inv[1][2] * n.y + inv[2][2] * n.z,
+        )
+    }
+
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray {
+            orig: self.point(r.orig),
+            dir: self.vector(r.dir),
+            time: r.time,
+        }
+    }
+
+    /// Box around all eight transformed corners of `b`.
+    pub fn bbox(&self, b: &Aabb) -> Aabb {
+        let mut min = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+
+        for corner in corners(b) {
+            let p = self.point(corner);
+
+            min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+
+        Aabb::new(min, max)
+    }
+
+    /// Determinant of the linear part, the factor volumes are scaled by.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+impl ops::Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform {
+            m: multiply(&self.m, &rhs.m),
+            inv: multiply(&rhs.inv, &self.inv),
+        }
+    }
+}
+
+fn corners(b: &Aabb) -> [Vec3; 8] {
+    std::array::from_fn(|i| {
+        Vec3::new(
+            if i & 1 == 0 { b.min.x } else { b.max.x },
+            if i & 2 == 0 { b.min.y } else { b.max.y },
+            if i & 4 == 0 { b.min.z } else { b.max.z },
+        )
+    })
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.; 4]; 4];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+
+    out
+}
+
+fn transpose(m: &Matrix) -> Matrix {
+    let mut out = [[0.; 4]; 4];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = m[j][i];
+        }
+    }
+
+    out
+}
+
+// Gauss-Jordan elimination with partial pivoting.
+fn invert(m: &Matrix) -> Option<Matrix> {
+    let mut a = *m;
+    let mut inv = IDENTITY;
+
+    for col in 0..4 {
+        let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+
+        let scale = 1. / a[col][col];
+        for j in 0..4 {
+            a[col][j] *= scale;
+            inv[col][j] *= scale;
+        }
+
+        for row in 0..4 {
+            if row == col {
+                continue;
+            }
+
+            let factor = a[row][col];
+            for j in 0..4 {
+                a[row][j] -= factor * a[col][j];
+                inv[row][j] -= factor * inv[col][j];
+            }
+        }
+    }
+
+    Some(inv)
+}
+
+/// Unit quaternion describing a rotation, which unlike matrices can be
+/// interpolated smoothly.
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub w: f64,
+    pub v: Vec3,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::identity()
+    }
+}
+
+impl Quaternion {
+    pub fn identity() -> Self {
+        Quaternion {
+            w: 1.,
+            v: Vec3::new(0., 0., 0.),
+        }
+    }
+
+    /// Same rotation as `Transform::rotate(axis, deg)`.
+    pub fn from_axis_angle(axis: Vec3, deg: f64) -> Self {
+        let (sin, cos) = (deg_to_rad(deg) / 2.).sin_cos();
+        Quaternion {
+            w: cos,
+            v: sin * unit_vector(axis),
+        }
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + dot(self.v, other.v)
+    }
+
+    fn normalized(&self) -> Quaternion {
+        let len = self.dot(self).sqrt();
+        Quaternion {
+            w: self.w / len,
+            v: self.v / len,
+        }
+    }
+
+    /// Rotation angle in radians between two orientations.
+    pub fn angle_to(&self, other: &Quaternion) -> f64 {
+        2. * self.dot(other).abs().min(1.).acos()
+    }
+
+    /// Spherical interpolation at constant angular speed, along the shorter
+    /// of the two arcs.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0. {
+            cos = -cos;
+            other = Quaternion {
+                w: -other.w,
+                v: -other.v,
+            };
+        }
+
+        // Nearly parallel, where the formula below is badly conditioned.
+        if cos > 0.9995 {
+            return Quaternion {
+                w: self.w + t * (other.w - self.w),
+                v: self.v + t * (other.v - self.v),
+            }
+            .normalized();
+        }
+
+        let theta = cos.acos();
+        let sin = theta.sin();
+        let a = ((1. - t) * theta).sin() / sin;
+        let b = (t * theta).sin() / sin;
+
+        Quaternion {
+            w: a * self.w + b * other.w,
+            v: a * self.v + b * other.v,
+        }
+        .normalized()
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        let Quaternion { w, v } = self.normalized();
+        let (x, y, z) = (v.x, v.y, v.z);
+
+        let mut m = IDENTITY;
+        m[0] = [
+            1. - 2. * (y * y + z * z),
+            2. * (x * y - w * z),
+            2. * (x * z + w * y),
+            0.,
+        ];
+        m[1] = [
+            2. * (x * y + w * z),
+            1. - 2. * (x * x + z * z),
+            2. * (y * z - w * x),
+            0.,
+        ];
+        m[2] = [
+            2. * (x * z - w * y),
+            2. * (y * z + w * x),
+            1. - 2. * (x * x + y * y),
+            0.,
+        ];
+
+        Transform::from_rotation(m)
+    }
+}
+
+/// Pose of an object at one point in time, applied as scale, then rotation,
+/// then translation.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f64) -> Self {
+        Keyframe {
+            time,
+            translation: Vec3::new(0., 0., 0.),
+            rotation: Quaternion::identity(),
+            scale: Vec3::new(1., 1., 1.),
+        }
+    }
+
+    pub fn translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Quaternion) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    fn transform(&self) -> Transform {
+        Transform::scale(self.scale)
+            .then(&self.rotation.to_transform())
+            .then(&Transform::translate(self.translation))
+    }
+}
+
+/// Transform interpolated between keyframes: linearly for translation and
+/// scale, by slerp for rotation. Rotations between neighbouring keyframes
+/// take the shorter way round, so spinning objects need a keyframe at least
+/// every half turn. Before the first and after the last keyframe the pose
+/// is held.
+#[derive(Debug, Clone)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        if keyframes.is_empty() {
+            panic!("Animation needs at least one keyframe!");
+        }
+
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        AnimatedTransform { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        self.pose(time).transform()
+    }
+
+    fn pose(&self, time: f64) -> Keyframe {
+        let keys = &self.keyframes;
+        let last = keys.len() - 1;
+
+        if time <= keys[0].time {
+            return keys[0];
+        }
+        if time >= keys[last].time {
+            return keys[last];
+        }
+
+        // First keyframe after `time`, which is never the first one.
+        let i = keys.partition_point(|k| k.time <= time);
+        let (a, b) = (&keys[i - 1], &keys[i]);
+        let t = (time - a.time) / (b.time - a.time);
+
+        Keyframe {
+            time,
+            translation: a.translation + t * (b.translation - a.translation),
+            rotation: a.rotation.slerp(&b.rotation, t),
+            scale: a.scale + t * (b.scale - a.scale),
+        }
+    }
+
+    /// Box around `b` for every pose in `[time0, time1]`. Poses are sampled
+    /// densely and the box is padded by how far a rotating corner can stray
+    /// from the chord between two samples.
+    pub fn bbox(&self, b: &Aabb, time0: f64, time1: f64) -> Aabb {
+        const STEPS: usize = 32;
+
+        let mut times: Vec<f64> = self
+            .keyframes
+            .iter()
+            .map(|k| k.time)
+            .filter(|&t| t > time0 && t < time1)
+            .collect();
+        times.push(time0);
+        times.push(time1);
+        times.sort_by(f64::total_cmp);
+
+        // Distance of the furthest corner from the origin, after scaling.
+        let max_scale = self.keyframes.iter().fold(0., |m: f64, k| {
+            m.max(k.scale.x.abs())
+                .max(k.scale.y.abs())
+                .max(k.scale.z.abs())
+        });
+        let radius = max_scale * corners(b).iter().fold(0., |m: f64, c| m.max(c.len()));
+
+        let mut output = self.at(time0).bbox(b);
+        let mut pad: f64 = 0.;
+
+        for span in times.windows(2) {
+            let mut prev = self.pose(span[0]);
+            for step in 1..=STEPS {
+                let time = span[0] + (span[1] - span[0]) * step as f64 / STEPS as f64;
+                let pose = self.pose(time);
+
+                output = surrounding_box(output, pose.transform().bbox(b));
+                let angle = prev.rotation.angle_to(&pose.rotation);
+                pad = pad.max(radius * (1. - (angle / 2.).cos()));
+                prev = pose;
+            }
+        }
+
+        let pad = Vec3::new(pad, pad, pad);
+        Aabb::new(output.min - pad, output.max + pad)
+    }
+}
+
+/// Places a shared object in the world with a transform. The object itself
+/// is never copied, so one mesh can be instanced any number of times.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    motion: Option<AnimatedTransform>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        Instance {
+            object,
+            transform,
+            motion: None,
+        }
+    }
+
+    /// An instance that moves over time, posed at `ray.time`.
+    pub fn animated(object: Arc<dyn Hittable>, motion: AnimatedTransform) -> Self {
+        Instance::new(object, Transform::identity()).with_motion(motion)
+    }
+
+    /// Animates the instance, with `motion` applied after its static
+    /// transform.
+    pub fn with_motion(mut self, motion: AnimatedTransform) -> Self {
+        self.motion = Some(motion);
+        self
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn motion(&self) -> Option<&AnimatedTransform> {
+        self.motion.as_ref()
+    }
+
+    pub fn transform_at(&self, time: f64) -> Transform {
+        match &self.motion {
+            Some(motion) => self.transform.then(&motion.at(time)),
+            None => self.transform,
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        let transform = self.transform_at(r.time);
+
+        // The direction is not normalized, so `t` is the same in both spaces.
+        let local = transform.inverse().ray(r);
+        if !self.object.hit(&local, t_min, t_max, rec) {
+            return false;
+        }
+
+        // The inverse transpose keeps the normal on the side of the ray, so
+        // `front` still holds.
+        rec.p = transform.point(rec.p);
+        rec.norm = unit_vector(transform.normal(rec.norm));
+        rec.tangent = transform.vector(rec.tangent);
+        true
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64, output_box: &mut Aabb) -> bool {
+        let mut local = Aabb::default();
+        if !self.object.bounding_box(time0, time1, &mut local) {
+            return false;
+        }
+
+        let placed = self.transform.bbox(&local);
+        *output_box = match &self.motion {
+            Some(motion) => motion.bbox(&placed, time0, time1),
+            None => placed,
+        };
+        true
+    }
+
+    // Solid angles are distorted by non-rigid transforms, so the local
+    // density is scaled by the Jacobian of mapping world directions into
+    // object space. Moving instances are sampled in their pose at `time`.
+    fn pdf_value(&self, origin: Vec3, dir: Vec3, time: f64) -> f64 {
+        let inv = self.transform_at(time).inverse();
+        let local_dir = inv.vector(unit_vector(dir));
+        let pdf = self.object.pdf_value(inv.point(origin), local_dir, time);
+        let len = local_dir.len();
+
+        pdf * inv.determinant().abs() / (len * len * len)
+    }
+
+    fn random(&self, origin: Vec3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        let transform = self.transform_at(time);
+        let local_origin = transform.inverse().point(origin);
+        transform.vector(self.object.random(local_origin, time, sampler))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MatType;
+    use crate::sphere::Sphere;
+    use crate::INF;
+
+    fn assert_matrix_eq(a: &Matrix, b: &Matrix) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a[i][j] - b[i][j]).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert!((a - b).len() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    // Scale, rotation and translation, with a reflection thrown in.
+    fn mixed() -> Transform {
+        Transform::scale(Vec3::new(2., -0.5, 3.))
+            .then(&Transform::rotate(Vec3::new(1., 2., 3.), 37.))
+            .then(&Transform::translate(Vec3::new(4., -1., 0.5)))
+    }
+
+    #[test]
+    fn transform_times_inverse_is_identity() {
+        let transforms = [
+            Transform::translate(Vec3::new(1., 2., 3.)),
+            Transform::scale(Vec3::new(2., 3., 4.)),
+            Transform::rotate_y(30.),
+            mixed(),
+        ];
+
+        for t in transforms {
+            assert_matrix_eq(&(t * t.inverse()).matrix(), &IDENTITY);
+            assert_matrix_eq(&(t.inverse() * t).matrix(), &IDENTITY);
+
+            let from_matrix = Transform::from_matrix(t.matrix()).unwrap();
+            assert_matrix_eq(&from_matrix.inverse().matrix(), &t.inverse().matrix());
+        }
+
+        let mut singular = IDENTITY;
+        singular[1][1] = 0.;
+        assert!(Transform::from_matrix(singular).is_none());
+    }
+
+    #[test]
+    fn then_applies_transforms_in_order() {
+        let p = Vec3::new(1., 0., 0.);
+        let rotate_then_move = Transform::rotate_z(90.).then(&Transform::translate(p));
+        assert_vec_eq(rotate_then_move.point(p), Vec3::new(1., 1., 0.));
+
+        let move_then_rotate = Transform::translate(p).then(&Transform::rotate_z(90.));
+        assert_vec_eq(move_then_rotate.point(p), Vec3::new(0., 2., 0.));
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_to_transformed_surfaces() {
+        let t = mixed();
+        let n = Vec3::new(1., 1., 0.);
+        for tangent in [Vec3::new(1., -1., 0.), Vec3::new(0., 0., 1.)] {
+            assert!(dot(t.normal(n), t.vector(tangent)).abs() < 1e-9);
+        }
+        assert!((t.determinant() + 3.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn instance_hits_the_transformed_object() {
+        let sphere = Arc::new(Sphere::new(Vec3::new(0., 0., 0.), 1., MatType::default()));
+        let transform = Transform::scale(Vec3::new(1., 2., 1.))
+            .then(&Transform::translate(Vec3::new(3., 0., 0.)));
+        let instance = Instance::new(sphere, transform);
+
+        // The stretched sphere reaches up to y = 2 above its new center.
+        let r = Ray {
+            orig: Vec3::new(3., 5., 0.),
+            dir: Vec3::new(0., -1., 0.),
+            time: 0.,
+        };
+        let mut rec = HitRecord::new();
+        assert!(instance.hit(&r, 0.001, INF, &mut rec));
+        assert!((rec.t - 3.).abs() < 1e-9);
+        assert_vec_eq(rec.p, Vec3::new(3., 2., 0.));
+        assert_vec_eq(rec.norm, Vec3::new(0., 1., 0.));
+        assert!(rec.front);
+
+        let mut bbox = Aabb::default();
+        assert!(instance.bounding_box(0., 1., &mut bbox));
+        assert_vec_eq(bbox.min, Vec3::new(2., -2., -1.));
+        assert_vec_eq(bbox.max, Vec3::new(4., 2., 1.));
+    }
+
+    fn assert_same_rotation(a: &Quaternion, b: &Quaternion) {
+        assert!(a.angle_to(b) < 1e-6, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn slerp_returns_endpoints_and_halves_the_angle() {
+        let a = Quaternion::from_axis_angle(Vec3::new(0., 1., 0.), 20.);
+        let b = Quaternion::from_axis_angle(Vec3::new(1., 1., 0.), 130.);
+
+        assert_same_rotation(&a.slerp(&b, 0.), &a);
+        assert_same_rotation(&a.slerp(&b, 1.), &b);
+        let mid = a.slerp(&b, 0.5);
+        assert!((a.angle_to(&mid) - b.angle_to(&mid)).abs() < 1e-9);
+        assert!((2. * a.angle_to(&mid) - a.angle_to(&b)).abs() < 1e-9);
+
+        // The negated quaternion is the same rotation, reached the short way.
+        let minus_b = Quaternion { w: -b.w, v: -b.v };
+        assert_same_rotation(&a.slerp(&minus_b, 1.), &b);
+        assert_same_rotation(&a.slerp(&b, 0.25), &a.slerp(&minus_b, 0.25));
+
+        // Nearly identical rotations take the linear fallback.
+        let c = Quaternion::from_axis_angle(Vec3::new(0., 1., 0.), 20.5);
+        assert_same_rotation(&a.slerp(&c, 1.), &c);
+    }
+
+    #[test]
+    fn quaternion_matches_matrix_rotation() {
+        let axis = Vec3::new(1., -2., 0.5);
+        let q = Quaternion::from_axis_angle(axis, 75.);
+        assert_matrix_eq(
+            &q.to_transform().matrix(),
+            &Transform::rotate(axis, 75.).matrix(),
+        );
+    }
+
+    fn spinning_arm() -> AnimatedTransform {
+        let axis = Vec3::new(0., 0., 1.);
+        AnimatedTransform::new(vec![
+            Keyframe::new(1.)
+                .translation(Vec3::new(0., 0., 4.))
+                .rotation(Quaternion::from_axis_angle(axis, 170.)),
+            Keyframe::new(0.),
+            Keyframe::new(0.5)
+                .scale(Vec3::new(2., 2., 2.))
+                .rotation(Quaternion::from_axis_angle(axis, 90.)),
+        ])
+    }
+
+    #[test]
+    fn animation_hits_keyframes_and_holds_outside() {
+        let motion = spinning_arm();
+        for key in motion.keyframes().to_vec() {
+            assert_matrix_eq(&motion.at(key.time).matrix(), &key.transform().matrix());
+        }
+
+        assert_matrix_eq(&motion.at(-1.).matrix(), &IDENTITY);
+        assert_matrix_eq(&motion.at(2.).matrix(), &motion.at(1.).matrix());
+
+        // Halfway between the first two keyframes.
+        let p = motion.at(0.25).point(Vec3::new(1., 0., 0.));
+        let angle = 45f64.to_radians();
+        assert_vec_eq(p, 1.5 * Vec3::new(angle.cos(), angle.sin(), 0.));
+    }
+
+    #[test]
+    fn animated_bounds_cover_the_whole_motion() {
+        let sphere = Arc::new(Sphere::new(Vec3::new(3., 0., 0.), 0.5, MatType::default()));
+        let instance = Instance::animated(sphere, spinning_arm());
+
+        let mut bbox = Aabb::default();
+        assert!(instance.bounding_box(0., 1., &mut bbox));
+
+        let center = Vec3::new(3., 0., 0.);
+        for i in 0..=1000 {
+            let transform = instance.transform_at(i as f64 / 1000.);
+            let c = transform.point(center);
+            let radius = 0.5 * transform.vector(Vec3::new(1., 0., 0.)).len();
+            for a in 0..3 {
+                assert!(c[a] - radius >= bbox.min[a] - 1e-9);
+                assert!(c[a] + radius <= bbox.max[a] + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn animated_instance_is_hit_where_it_is_at_ray_time() {
+        let sphere = Arc::new(Sphere::new(Vec3::new(0., 0., 0.), 1., MatType::default()));
+        let instance = Instance::animated(
+            sphere,
+            AnimatedTransform::new(vec![
+                Keyframe::new(0.),
+                Keyframe::new(1.).translation(Vec3::new(10., 0., 0.)),
+            ]),
+        );
+
+        let ray_at = |time| Ray {
+            orig: Vec3::new(5., 0., 5.),
+            dir: Vec3::new(0., 0., -1.),
+            time,
+        };
+        let mut rec = HitRecord::new();
+        assert!(instance.hit(&ray_at(0.5), 0.001, INF, &mut rec));
+        assert!((rec.t - 4.).abs() < 1e-9);
+        assert!(!instance.hit(&ray_at(0.), 0.001, INF, &mut rec));
+        assert!(!instance.hit(&ray_at(1.), 0.001, INF, &mut rec));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::MatType;
+use crate::ray::Ray;
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    /// Per-vertex shading normals, the flat face normal is used if absent.
+    pub normals: Option<[Vec3; 3]>,
+    pub uvs: Option<[(f64, f64); 3]>,
+    pub material: MatType,
+}
+
+impl Triangle {
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, material: MatType) -> Self {
+        Triangle {
+            vertices: [p0, p1, p2],
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit<'a>(&'a self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord<'a>) -> bool {
+        let [p0, p1, p2] = self.vertices;
+
+        match intersect(r, p0, p1, p2, t_min, t_max) {
+            Some((t, b1, b2)) => {
+                let hit = TriangleHit { t, b1, b2 };
+                hit.fill(r, &self.vertices, self.normals, self.uvs, rec);
+                rec.material = &self.material;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64, output_box: &mut Aabb) -> bool {
+        *output_box = triangle_bbox(&self.vertices);
+        true
+    }
+}
+
+pub(crate) fn triangle_bbox(vertices: &[Vec3; 3]) -> Aabb {
+    let [p0, p1, p2] = *vertices;
+    let min = Vec3::new(
+        p0.x.min(p1.x).min(p2.x),
+        p0.y.min(p1.y).min(p2.y),
+        p0.z.min(p1.z).min(p2.z),
+    );
+    let max = Vec3::new(
+        p0.x.max(p1.x).max(p2.x),
+        p0.y.max(p1.y).max(p2.y),
+        p0.z.max(p1.z).max(p2.z),
+    );
+
+    Aabb::new(min, max).pad(1e-4)
+}
+
+/// Möller–Trumbore ray/triangle test. Returns the ray parameter and the
+/// barycentric weights of `p1` and `p2`.
+pub(crate) fn intersect(
+    r: &Ray,
+    p0: Vec3,
+    p1: Vec3,
+    p2: Vec3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let pvec = cross(r.dir, e2);
+    let det = dot(e1, pvec);
+
+    // Rays parallel to the triangle's plane.
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_det = 1. / det;
+    let tvec = r.orig - p0;
+    let b1 = dot(tvec, pvec) * inv_det;
+    if !(0. ..=1.).contains(&b1) {
+        return None;
+    }
+
+    let qvec = cross(tvec, e1);
+    let b2 = dot(r.dir, qvec) * inv_det;
+    if b2 < 0. || b1 + b2 > 1. {
+        return None;
+    }
+
+    let t = dot(e2, qvec) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+// Derivative of the position with respect to `u`, along the first edge if
+// the texture coordinates are missing or degenerate.
+fn triangle_dpdu(vertices: &[Vec3; 3], uvs: Option<[(f64, f64); 3]>) -> Vec3 {
+    let [p0, p1, p2] = *vertices;
+    let (e1, e2) = (p1 - p0, p2 - p0);
+
+    if let Some([uv0, uv1, uv2]) = uvs {
+        let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+        let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+        let det = du1 * dv2 - dv1 * du2;
+        if det.abs() > 1e-12 {
+            return (dv2 * e1 - dv1 * e2) / det;
+        }
+    }
+
+    e1
+}
+
+pub(crate) struct TriangleHit {
+    pub t: f64,
+    pub b1: f64,
+    pub b2: f64,
+}
+
+impl TriangleHit {
+    /// Fills everything but the material from the hit barycentrics.
+    pub fn fill(
+        &self,
+        r: &Ray,
+        vertices: &[Vec3; 3],
+        normals: Option<[Vec3; 3]>,
+        uvs: Option<[(f64, f64); 3]>,
+        rec: &mut HitRecord,
+    ) {
+        let b0 = 1. - self.b1 - self.b2;
+        let [p0, p1, p2] = *vertices;
+
+        rec.t = self.t;
+        rec.p = r.at(self.t);
+
+        (rec.u, rec.v) = match uvs {
+            Some([uv0, uv1, uv2]) => (
+                b0 * uv0.0 + self.b1 * uv1.0 + self.b2 * uv2.0,
+                b0 * uv0.1 + self.b1 * uv1.1 + self.b2 * uv2.1,
+            ),
+            None => (self.b1, self.b2),
+        };
+        rec.tangent = triangle_dpdu(vertices, uvs);
+
+        // The geometric normal decides which side was hit, the interpolated
+        // normal is then flipped onto that side for shading.
+        rec.set_face_normal(r, unit_vector(cross(p1 - p0, p2 - p0)));
+
+        if let Some([n0, n1, n2]) = normals {
+            let shading = unit_vector(b0 * n0 + self.b1 * n1 + self.b2 * n2);
+            rec.norm = if dot(shading, rec.norm) < 0. {
+                -shading
+            } else {
+                shading
+            };
+        }
+    }
+}
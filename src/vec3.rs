@@ -1,7 +1,7 @@
 use rand::Rng;
 use std::ops;
 
-#[derive(Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -13,25 +13,25 @@ impl Vec3 {
         Self { x: a, y: b, z: c }
     }
 
-    pub fn random() -> Self {
+    pub fn random(rng: &mut impl Rng) -> Self {
         Self {
-            x: rand::thread_rng().gen(),
-            y: rand::thread_rng().gen(),
-            z: rand::thread_rng().gen(),
+            x: rng.gen(),
+            y: rng.gen(),
+            z: rng.gen(),
         }
     }
 
-    pub fn random_range(min: f64, max: f64) -> Self {
+    pub fn random_range(rng: &mut impl Rng, min: f64, max: f64) -> Self {
         Self {
-            x: rand::thread_rng().gen_range(min..max),
-            y: rand::thread_rng().gen_range(min..max),
-            z: rand::thread_rng().gen_range(min..max),
+            x: rng.gen_range(min..max),
+            y: rng.gen_range(min..max),
+            z: rng.gen_range(min..max),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere(rng: &mut impl Rng) -> Self {
         loop {
-            let p = Vec3::random_range(-1., 1.);
+            let p = Vec3::random_range(rng, -1., 1.);
             if dot(p, p) >= 1. {
                 continue;
             }
@@ -40,13 +40,9 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Self {
+    pub fn random_in_unit_disk(rng: &mut impl Rng) -> Self {
         loop {
-            let p = Vec3::new(
-                rand::thread_rng().gen_range(-1. ..1.),
-                rand::thread_rng().gen_range(-1. ..1.),
-                0.,
-            );
+            let p = Vec3::new(rng.gen_range(-1. ..1.), rng.gen_range(-1. ..1.), 0.);
             if p.len_2() >= 1. {
                 continue;
             }
@@ -54,8 +50,8 @@ impl Vec3 {
         }
     }
 
-    pub fn random_unit() -> Self {
-        unit_vector(Vec3::random_in_unit_sphere())
+    pub fn random_unit(rng: &mut impl Rng) -> Self {
+        unit_vector(Vec3::random_in_unit_sphere(rng))
     }
 
     pub fn len_2(&self) -> f64 {